@@ -1,23 +1,40 @@
-use ash::{Device, vk, vk::Handle};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Mutex, mpsc},
+    thread,
+};
+
+use ash::{Device, ext, khr, prelude::VkResult, vk, vk::Handle};
 
 use crate::{HandleVisitor, VisitHandles};
 
 /// Helper for deferred destruction of resources used within a frame
+///
+/// Must be [`clear`](Self::clear)ed before being dropped. In debug builds, dropping a graveyard
+/// with pending entries panics.
 pub struct Graveyard {
     frames: Box<[Frame]>,
     cursor: usize,
+    extensions: Extensions,
 }
 
 impl Graveyard {
     /// Construct a graveyard that destroys resources `depth` frames after they're passed to `inter`
     pub fn new(depth: usize) -> Self {
+        Self::with_extensions(depth, Extensions::default())
+    }
+
+    /// Like [`new`](Self::new), but able to destroy extension object types using `extensions`
+    pub fn with_extensions(depth: usize, extensions: Extensions) -> Self {
         Self {
             frames: (0..depth)
                 .map(|_| Frame {
-                    handles: Vec::new(),
+                    entries: Vec::new(),
                 })
                 .collect(),
             cursor: 0,
+            extensions,
         }
     }
 
@@ -32,13 +49,19 @@ impl Graveyard {
         unsafe {
             self.cursor = (self.cursor + 1) % self.frames.len();
             let frame = &mut self.frames[self.cursor];
-            for (ty, handle) in frame.handles.drain(..) {
-                destroy_dynamic(device, ty, handle);
+            for entry in frame.entries.drain(..) {
+                match entry {
+                    Entry::Handle(ty, handle) => self.extensions.destroy(device, ty, handle),
+                    Entry::Deferred(f) => (f.0)(device),
+                }
             }
         }
     }
 
     /// Free the resources in `resources` after `self.depth()` frames
+    ///
+    /// # Panics
+    /// If any resource's type is not supported by [`Extensions::supports`]
     pub fn inter(&mut self, resources: impl VisitHandles) {
         resources.visit_handles(self);
     }
@@ -47,14 +70,27 @@ impl Graveyard {
     ///
     /// Escape hatch for stuff that doesn't implement `VisitHandles`
     pub fn inter_handle<T: Handle>(&mut self, handle: T) {
-        self.frames[self.cursor].push(handle)
+        self.inter_handle_dynamic(T::TYPE, handle.as_raw());
     }
 
     /// Free `handle` after `self.depth()` frames
     ///
     /// Escape hatch for dynamically typed handles
     pub fn inter_handle_dynamic(&mut self, ty: vk::ObjectType, handle: u64) {
-        self.frames[self.cursor].handles.push((ty, handle))
+        assert_supported(&self.extensions, ty);
+        self.push(Entry::Handle(ty, handle));
+    }
+
+    /// Run `f` after `self.depth()` frames
+    ///
+    /// For cleanup that isn't a matter of destroying handles, such as freeing descriptor sets or
+    /// recycling bindless slots. Runs in order with resources passed to `inter`.
+    pub fn defer(&mut self, f: impl FnOnce(&Device) + Send + 'static) {
+        self.push(Entry::Deferred(Deferred(Box::new(f))));
+    }
+
+    fn push(&mut self, entry: Entry) {
+        self.frames[self.cursor].entries.push(entry);
     }
 
     /// Free all resources immediately
@@ -65,6 +101,81 @@ impl Graveyard {
             }
         }
     }
+
+    /// Number of handles and deferred closures awaiting destruction
+    pub fn len(&self) -> usize {
+        self.frames.iter().map(|frame| frame.entries.len()).sum()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.frames.iter().all(|frame| frame.entries.is_empty())
+    }
+
+    /// Summarize what's awaiting destruction
+    pub fn stats(&self) -> GraveyardStats {
+        let mut stats = GraveyardStats::default();
+        for i in 0..self.frames.len() {
+            // Start from the current frame and work backwards
+            let frame = &self.frames[(self.cursor + self.frames.len() - i) % self.frames.len()];
+            stats.per_frame.push(frame.entries.len());
+            for entry in &frame.entries {
+                match *entry {
+                    Entry::Handle(ty, _) => *stats.handles.entry(ty).or_default() += 1,
+                    Entry::Deferred(_) => stats.deferred += 1,
+                }
+            }
+        }
+        stats
+    }
+}
+
+impl Drop for Graveyard {
+    fn drop(&mut self) {
+        if cfg!(debug_assertions) && !thread::panicking() && !self.is_empty() {
+            panic!(
+                "graveyard dropped without being cleared, leaking {}",
+                self.stats()
+            );
+        }
+    }
+}
+
+/// Contents of a [`Graveyard`] awaiting destruction
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct GraveyardStats {
+    /// Number of handles of each type
+    pub handles: BTreeMap<vk::ObjectType, usize>,
+    /// Number of closures passed to [`Graveyard::defer`]
+    pub deferred: usize,
+    /// Number of handles and closures in each frame, starting from the current one
+    pub per_frame: Vec<usize>,
+}
+
+impl GraveyardStats {
+    /// Number of handles and closures across all frames
+    pub fn total(&self) -> usize {
+        self.handles.values().sum::<usize>() + self.deferred
+    }
+}
+
+impl fmt::Display for GraveyardStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} pending (", self.total())?;
+        for (i, (ty, count)) in self.handles.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{ty:?}: {count}")?;
+        }
+        if !self.handles.is_empty() && self.deferred != 0 {
+            write!(f, ", ")?;
+        }
+        if self.deferred != 0 {
+            write!(f, "deferred: {}", self.deferred)?;
+        }
+        write!(f, "), per frame: {:?}", self.per_frame)
+    }
 }
 
 impl HandleVisitor for Graveyard {
@@ -75,19 +186,344 @@ impl HandleVisitor for Graveyard {
 
 /// A collection of resources to be freed in the future
 struct Frame {
+    entries: Vec<Entry>,
+}
+
+/// Something to clean up when a frame is reused
+enum Entry {
+    Handle(vk::ObjectType, u64),
+    Deferred(Deferred),
+}
+
+struct Deferred(Box<dyn FnOnce(&Device) + Send>);
+
+// Safety: the closure is only accessible, and hence only called, through exclusive ownership, so
+// concurrent shared access can't observe it.
+unsafe impl Sync for Deferred {}
+
+/// A [`Graveyard`] that resources can be interred into from any thread
+///
+/// Interment sends resources over a channel without blocking. They're collected into the current
+/// frame by [`begin_frame`](Self::begin_frame), so resources interred concurrently with
+/// `begin_frame` may be attributed to either frame.
+pub struct SharedGraveyard {
+    send: mpsc::Sender<Entry>,
+    /// Copy of the graveyard's extensions, for validating types without locking
+    extensions: Extensions,
+    inner: Mutex<(Graveyard, mpsc::Receiver<Entry>)>,
+}
+
+impl SharedGraveyard {
+    /// Construct a graveyard that destroys resources `depth` frames after they're passed to `inter`
+    pub fn new(depth: usize) -> Self {
+        Self::with_extensions(depth, Extensions::default())
+    }
+
+    /// Like [`new`](Self::new), but able to destroy extension object types using `extensions`
+    pub fn with_extensions(depth: usize, extensions: Extensions) -> Self {
+        let (send, recv) = mpsc::channel();
+        Self {
+            send,
+            extensions: extensions.clone(),
+            inner: Mutex::new((Graveyard::with_extensions(depth, extensions), recv)),
+        }
+    }
+
+    /// Number of frames after which resources passed to `inter` are destroyed
+    pub fn depth(&self) -> usize {
+        self.inner.lock().unwrap().0.depth()
+    }
+
+    /// Free the resources in `resources` after `self.depth()` frames
+    ///
+    /// # Panics
+    /// If any resource's type is not supported by [`Extensions::supports`]
+    pub fn inter(&self, resources: impl VisitHandles) {
+        struct Visitor<'a>(&'a SharedGraveyard);
+        impl HandleVisitor for Visitor<'_> {
+            fn visit_dynamic(&mut self, ty: vk::ObjectType, handle: u64) {
+                self.0.inter_handle_dynamic(ty, handle);
+            }
+        }
+        resources.visit_handles(&mut Visitor(self));
+    }
+
+    /// Free `handle` after `self.depth()` frames
+    ///
+    /// Escape hatch for stuff that doesn't implement `VisitHandles`
+    pub fn inter_handle<T: Handle>(&self, handle: T) {
+        self.inter_handle_dynamic(T::TYPE, handle.as_raw());
+    }
+
+    /// Free `handle` after `self.depth()` frames
+    ///
+    /// Escape hatch for dynamically typed handles
+    pub fn inter_handle_dynamic(&self, ty: vk::ObjectType, handle: u64) {
+        assert_supported(&self.extensions, ty);
+        self.send.send(Entry::Handle(ty, handle)).unwrap();
+    }
+
+    /// Run `f` after `self.depth()` frames
+    ///
+    /// See [`Graveyard::defer`].
+    pub fn defer(&self, f: impl FnOnce(&Device) + Send + 'static) {
+        self.send
+            .send(Entry::Deferred(Deferred(Box::new(f))))
+            .unwrap();
+    }
+
+    /// Free resources from `depth` frames ago
+    ///
+    /// # Safety
+    /// `device` must own every interred resource
+    pub unsafe fn begin_frame(&self, device: &Device) {
+        let mut inner = self.inner.lock().unwrap();
+        let (graveyard, recv) = &mut *inner;
+        collect(graveyard, recv);
+        unsafe {
+            graveyard.begin_frame(device);
+        }
+    }
+
+    /// Free all resources immediately
+    ///
+    /// # Safety
+    /// `device` must own every interred resource, and no interred resource may be in use
+    pub unsafe fn clear(&self, device: &Device) {
+        let mut inner = self.inner.lock().unwrap();
+        let (graveyard, recv) = &mut *inner;
+        collect(graveyard, recv);
+        unsafe {
+            graveyard.clear(device);
+        }
+    }
+
+    /// Summarize what's awaiting destruction
+    pub fn stats(&self) -> GraveyardStats {
+        let mut inner = self.inner.lock().unwrap();
+        let (graveyard, recv) = &mut *inner;
+        collect(graveyard, recv);
+        graveyard.stats()
+    }
+}
+
+impl Drop for SharedGraveyard {
+    fn drop(&mut self) {
+        // Let `Graveyard`'s leak check see everything
+        let (graveyard, recv) = self.inner.get_mut().unwrap_or_else(|e| e.into_inner());
+        collect(graveyard, recv);
+    }
+}
+
+/// Move entries sent to a [`SharedGraveyard`] into its current frame
+fn collect(graveyard: &mut Graveyard, recv: &mpsc::Receiver<Entry>) {
+    for entry in recv.try_iter() {
+        graveyard.push(entry);
+    }
+}
+
+/// Helper for deferred destruction of resources used by work on one or more timelines
+///
+/// Unlike [`Graveyard`], destruction is driven by timeline semaphore values, such as those of
+/// [`ParallelQueue::semaphore`](crate::ParallelQueue::semaphore) and
+/// [`Work::time`](crate::parallel_queue::Work::time), so it needn't be aligned to frames.
+#[derive(Default)]
+pub struct TimelineGraveyard {
+    graves: Vec<Grave>,
+    extensions: Extensions,
+}
+
+impl TimelineGraveyard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Like [`new`](Self::new), but able to destroy extension object types using `extensions`
+    pub fn with_extensions(extensions: Extensions) -> Self {
+        Self {
+            graves: Vec::new(),
+            extensions,
+        }
+    }
+
+    /// Free the resources in `resources` once every timeline semaphore in `waits` reaches its
+    /// associated value
+    ///
+    /// # Panics
+    /// If any resource's type is not supported by [`Extensions::supports`]
+    pub fn inter(&mut self, waits: &[(vk::Semaphore, u64)], resources: impl VisitHandles) {
+        let mut grave = Grave {
+            waits: waits.into(),
+            handles: Vec::new(),
+        };
+        resources.visit_handles(&mut grave);
+        for &(ty, _) in &grave.handles {
+            assert_supported(&self.extensions, ty);
+        }
+        self.graves.push(grave);
+    }
+
+    /// Free `handle` once every timeline semaphore in `waits` reaches its associated value
+    ///
+    /// Escape hatch for stuff that doesn't implement `VisitHandles`
+    pub fn inter_handle<T: Handle>(&mut self, waits: &[(vk::Semaphore, u64)], handle: T) {
+        self.inter_handle_dynamic(waits, T::TYPE, handle.as_raw());
+    }
+
+    /// Free `handle` once every timeline semaphore in `waits` reaches its associated value
+    ///
+    /// Escape hatch for dynamically typed handles
+    pub fn inter_handle_dynamic(
+        &mut self,
+        waits: &[(vk::Semaphore, u64)],
+        ty: vk::ObjectType,
+        handle: u64,
+    ) {
+        assert_supported(&self.extensions, ty);
+        self.graves.push(Grave {
+            waits: waits.into(),
+            handles: vec![(ty, handle)],
+        });
+    }
+
+    /// Number of calls to `inter` and friends whose resources have not yet been freed
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.graves.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.graves.is_empty()
+    }
+
+    /// Free resources whose timeline values have been reached
+    ///
+    /// Each semaphore's counter is read at most once.
+    ///
+    /// # Safety
+    /// `device` must own every interred resource and semaphore
+    pub unsafe fn collect(&mut self, device: &Device) -> VkResult<()> {
+        unsafe {
+            for (ty, handle) in
+                self.take_reached(|semaphore| device.get_semaphore_counter_value(semaphore))?
+            {
+                self.extensions.destroy(device, ty, handle);
+            }
+        }
+        Ok(())
+    }
+
+    /// Free all resources immediately
+    ///
+    /// # Safety
+    /// `device` must own every interred resource, and no interred resource may be in use
+    pub unsafe fn clear(&mut self, device: &Device) {
+        for grave in self.graves.drain(..) {
+            for (ty, handle) in grave.handles {
+                unsafe {
+                    self.extensions.destroy(device, ty, handle);
+                }
+            }
+        }
+    }
+
+    /// Remove and return the handles of graves whose waits are all satisfied
+    fn take_reached(
+        &mut self,
+        mut counter: impl FnMut(vk::Semaphore) -> VkResult<u64>,
+    ) -> VkResult<Vec<(vk::ObjectType, u64)>> {
+        let mut counters = Vec::<(vk::Semaphore, u64)>::new();
+        let mut reached = Vec::with_capacity(self.graves.len());
+        for grave in &self.graves {
+            let mut done = true;
+            for &(semaphore, value) in &grave.waits {
+                let current = match counters.iter().find(|x| x.0 == semaphore) {
+                    Some(&(_, current)) => current,
+                    None => {
+                        let current = counter(semaphore)?;
+                        counters.push((semaphore, current));
+                        current
+                    }
+                };
+                if current < value {
+                    done = false;
+                    break;
+                }
+            }
+            reached.push(done);
+        }
+        let mut reached = reached.into_iter();
+        Ok(self
+            .graves
+            .extract_if(.., |_| reached.next().unwrap())
+            .flat_map(|grave| grave.handles)
+            .collect())
+    }
+}
+
+/// Resources to be freed once some timeline values are reached
+struct Grave {
+    waits: Box<[(vk::Semaphore, u64)]>,
     handles: Vec<(vk::ObjectType, u64)>,
 }
 
-impl Frame {
-    fn push<T: Handle>(&mut self, handle: T) {
-        self.handles.push((T::TYPE, handle.as_raw()));
+impl HandleVisitor for Grave {
+    fn visit_dynamic(&mut self, ty: vk::ObjectType, handle: u64) {
+        self.handles.push((ty, handle));
     }
 }
 
+fn assert_supported(extensions: &Extensions, ty: vk::ObjectType) {
+    assert!(extensions.supports(ty), "cannot destroy {:?} handles", ty);
+}
+
+/// Whether [`destroy_dynamic`] can destroy handles of type `ty`
+///
+/// True for every core object type that can be destroyed individually. Command buffers and
+/// descriptor sets are freed with their pools.
+pub fn can_destroy_dynamic(ty: vk::ObjectType) -> bool {
+    matches!(
+        ty,
+        vk::ObjectType::BUFFER
+            | vk::ObjectType::BUFFER_VIEW
+            | vk::ObjectType::IMAGE
+            | vk::ObjectType::IMAGE_VIEW
+            | vk::ObjectType::DEVICE_MEMORY
+            | vk::ObjectType::FRAMEBUFFER
+            | vk::ObjectType::SAMPLER
+            | vk::ObjectType::SAMPLER_YCBCR_CONVERSION
+            | vk::ObjectType::PIPELINE
+            | vk::ObjectType::PIPELINE_LAYOUT
+            | vk::ObjectType::PIPELINE_CACHE
+            | vk::ObjectType::DESCRIPTOR_SET_LAYOUT
+            | vk::ObjectType::DESCRIPTOR_POOL
+            | vk::ObjectType::DESCRIPTOR_UPDATE_TEMPLATE
+            | vk::ObjectType::QUERY_POOL
+            | vk::ObjectType::SEMAPHORE
+            | vk::ObjectType::FENCE
+            | vk::ObjectType::EVENT
+            | vk::ObjectType::RENDER_PASS
+            | vk::ObjectType::SHADER_MODULE
+            | vk::ObjectType::COMMAND_POOL
+            | vk::ObjectType::PRIVATE_DATA_SLOT
+    )
+}
+
+/// Destroy `handle`, which must be a core object of type `ty`
+///
+/// # Panics
+/// If `ty` is not supported, per [`can_destroy_dynamic`]
+///
+/// # Safety
+/// `handle` must be a valid handle of type `ty` owned by `device` and not in use
 pub unsafe fn destroy_dynamic(device: &Device, ty: vk::ObjectType, handle: u64) {
     unsafe {
         match ty {
             vk::ObjectType::BUFFER => device.destroy_buffer(vk::Buffer::from_raw(handle), None),
+            vk::ObjectType::BUFFER_VIEW => {
+                device.destroy_buffer_view(vk::BufferView::from_raw(handle), None)
+            }
             vk::ObjectType::IMAGE => device.destroy_image(vk::Image::from_raw(handle), None),
             vk::ObjectType::IMAGE_VIEW => {
                 device.destroy_image_view(vk::ImageView::from_raw(handle), None)
@@ -98,7 +534,226 @@ pub unsafe fn destroy_dynamic(device: &Device, ty: vk::ObjectType, handle: u64)
             vk::ObjectType::FRAMEBUFFER => {
                 device.destroy_framebuffer(vk::Framebuffer::from_raw(handle), None)
             }
-            _ => unimplemented!("cannot destroy {:?} handles", ty),
+            vk::ObjectType::SAMPLER => device.destroy_sampler(vk::Sampler::from_raw(handle), None),
+            vk::ObjectType::SAMPLER_YCBCR_CONVERSION => device.destroy_sampler_ycbcr_conversion(
+                vk::SamplerYcbcrConversion::from_raw(handle),
+                None,
+            ),
+            vk::ObjectType::PIPELINE => {
+                device.destroy_pipeline(vk::Pipeline::from_raw(handle), None)
+            }
+            vk::ObjectType::PIPELINE_LAYOUT => {
+                device.destroy_pipeline_layout(vk::PipelineLayout::from_raw(handle), None)
+            }
+            vk::ObjectType::PIPELINE_CACHE => {
+                device.destroy_pipeline_cache(vk::PipelineCache::from_raw(handle), None)
+            }
+            vk::ObjectType::DESCRIPTOR_SET_LAYOUT => device
+                .destroy_descriptor_set_layout(vk::DescriptorSetLayout::from_raw(handle), None),
+            vk::ObjectType::DESCRIPTOR_POOL => {
+                device.destroy_descriptor_pool(vk::DescriptorPool::from_raw(handle), None)
+            }
+            vk::ObjectType::DESCRIPTOR_UPDATE_TEMPLATE => device
+                .destroy_descriptor_update_template(
+                    vk::DescriptorUpdateTemplate::from_raw(handle),
+                    None,
+                ),
+            vk::ObjectType::QUERY_POOL => {
+                device.destroy_query_pool(vk::QueryPool::from_raw(handle), None)
+            }
+            vk::ObjectType::SEMAPHORE => {
+                device.destroy_semaphore(vk::Semaphore::from_raw(handle), None)
+            }
+            vk::ObjectType::FENCE => device.destroy_fence(vk::Fence::from_raw(handle), None),
+            vk::ObjectType::EVENT => device.destroy_event(vk::Event::from_raw(handle), None),
+            vk::ObjectType::RENDER_PASS => {
+                device.destroy_render_pass(vk::RenderPass::from_raw(handle), None)
+            }
+            vk::ObjectType::SHADER_MODULE => {
+                device.destroy_shader_module(vk::ShaderModule::from_raw(handle), None)
+            }
+            vk::ObjectType::COMMAND_POOL => {
+                device.destroy_command_pool(vk::CommandPool::from_raw(handle), None)
+            }
+            vk::ObjectType::PRIVATE_DATA_SLOT => {
+                device.destroy_private_data_slot(vk::PrivateDataSlot::from_raw(handle), None)
+            }
+            _ => panic!("cannot destroy {:?} handles", ty),
         }
     }
 }
+
+/// Extension loaders used to destroy extension object types
+#[derive(Clone, Default)]
+pub struct Extensions {
+    /// Enables destruction of `SWAPCHAIN_KHR` handles
+    pub swapchain: Option<khr::swapchain::Device>,
+    /// Enables destruction of `ACCELERATION_STRUCTURE_KHR` handles
+    pub acceleration_structure: Option<khr::acceleration_structure::Device>,
+    /// Enables destruction of `DEBUG_UTILS_MESSENGER_EXT` handles
+    pub debug_utils: Option<ext::debug_utils::Instance>,
+}
+
+impl Extensions {
+    /// Whether [`destroy`](Self::destroy) can destroy handles of type `ty`
+    pub fn supports(&self, ty: vk::ObjectType) -> bool {
+        match ty {
+            vk::ObjectType::SWAPCHAIN_KHR => self.swapchain.is_some(),
+            vk::ObjectType::ACCELERATION_STRUCTURE_KHR => self.acceleration_structure.is_some(),
+            vk::ObjectType::DEBUG_UTILS_MESSENGER_EXT => self.debug_utils.is_some(),
+            _ => can_destroy_dynamic(ty),
+        }
+    }
+
+    /// Like [`destroy_dynamic`], but also supporting extension object types for which a loader
+    /// is present
+    ///
+    /// # Panics
+    /// If `ty` is not supported, per [`supports`](Self::supports)
+    ///
+    /// # Safety
+    /// `handle` must be a valid handle of type `ty` owned by `device` and not in use, and each
+    /// loader must be associated with `device` or its instance
+    pub unsafe fn destroy(&self, device: &Device, ty: vk::ObjectType, handle: u64) {
+        unsafe {
+            match (ty, self) {
+                (
+                    vk::ObjectType::SWAPCHAIN_KHR,
+                    Self {
+                        swapchain: Some(swapchain),
+                        ..
+                    },
+                ) => swapchain.destroy_swapchain(vk::SwapchainKHR::from_raw(handle), None),
+                (
+                    vk::ObjectType::ACCELERATION_STRUCTURE_KHR,
+                    Self {
+                        acceleration_structure: Some(acceleration_structure),
+                        ..
+                    },
+                ) => acceleration_structure.destroy_acceleration_structure(
+                    vk::AccelerationStructureKHR::from_raw(handle),
+                    None,
+                ),
+                (
+                    vk::ObjectType::DEBUG_UTILS_MESSENGER_EXT,
+                    Self {
+                        debug_utils: Some(debug_utils),
+                        ..
+                    },
+                ) => debug_utils.destroy_debug_utils_messenger(
+                    vk::DebugUtilsMessengerEXT::from_raw(handle),
+                    None,
+                ),
+                _ => destroy_dynamic(device, ty, handle),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use super::*;
+
+    #[test]
+    fn timeline_collect() {
+        let a = vk::Semaphore::from_raw(1);
+        let b = vk::Semaphore::from_raw(2);
+        let buffer = |x| (vk::ObjectType::BUFFER, x);
+        let mut graveyard = TimelineGraveyard::new();
+        graveyard.inter_handle(&[(a, 1)], vk::Buffer::from_raw(10));
+        graveyard.inter_handle(&[(a, 2), (b, 1)], vk::Buffer::from_raw(11));
+        graveyard.inter_handle(&[(b, 3)], vk::Buffer::from_raw(12));
+        graveyard.inter_handle(&[], vk::Buffer::from_raw(13));
+
+        let mut reads = 0;
+        let reached = graveyard
+            .take_reached(|semaphore| {
+                reads += 1;
+                Ok(if semaphore == a { 2 } else { 0 })
+            })
+            .unwrap();
+        assert_eq!(reached, [buffer(10), buffer(13)]);
+        assert_eq!(reads, 2);
+        assert_eq!(graveyard.len(), 2);
+
+        let reached = graveyard
+            .take_reached(|semaphore| Ok(if semaphore == a { 2 } else { 1 }))
+            .unwrap();
+        assert_eq!(reached, [buffer(11)]);
+
+        assert!(
+            graveyard
+                .take_reached(|_| Err(vk::Result::ERROR_DEVICE_LOST))
+                .is_err()
+        );
+        assert_eq!(graveyard.len(), 1);
+    }
+
+    #[test]
+    fn supported_types() {
+        assert!(can_destroy_dynamic(vk::ObjectType::SAMPLER));
+        assert!(!can_destroy_dynamic(vk::ObjectType::DESCRIPTOR_SET));
+        assert!(!Extensions::default().supports(vk::ObjectType::SWAPCHAIN_KHR));
+    }
+
+    #[test]
+    fn stats() {
+        let mut graveyard = Graveyard::new(3);
+        graveyard.inter_handle(vk::Buffer::from_raw(1));
+        graveyard.inter_handle(vk::Image::from_raw(2));
+        graveyard.inter_handle(vk::Buffer::from_raw(3));
+        graveyard.defer(|_| ());
+        let stats = graveyard.stats();
+        assert_eq!(stats.total(), 4);
+        assert_eq!(graveyard.len(), 4);
+        assert_eq!(stats.handles[&vk::ObjectType::BUFFER], 2);
+        assert_eq!(stats.handles[&vk::ObjectType::IMAGE], 1);
+        assert_eq!(stats.per_frame, [4, 0, 0]);
+        assert_eq!(
+            stats.to_string(),
+            "4 pending (BUFFER: 2, IMAGE: 1, deferred: 1), per frame: [4, 0, 0]"
+        );
+        mem::forget(graveyard);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "graveyard dropped without being cleared")]
+    fn leak_check() {
+        Graveyard::new(2).inter_handle(vk::Buffer::from_raw(1));
+    }
+
+    #[test]
+    fn shared() {
+        let graveyard = SharedGraveyard::new(2);
+        thread::scope(|scope| {
+            for i in 0..4 {
+                let graveyard = &graveyard;
+                scope.spawn(move || {
+                    graveyard.inter_handle(vk::Buffer::from_raw(i + 1));
+                    graveyard.defer(|_| ());
+                });
+            }
+        });
+        let stats = graveyard.stats();
+        assert_eq!(stats.handles[&vk::ObjectType::BUFFER], 4);
+        assert_eq!(stats.deferred, 4);
+        assert_eq!(stats.per_frame, [8, 0]);
+        mem::forget(graveyard);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "graveyard dropped without being cleared")]
+    fn shared_leak_check() {
+        SharedGraveyard::new(2).inter_handle(vk::Buffer::from_raw(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot destroy SWAPCHAIN_KHR handles")]
+    fn reject_at_inter() {
+        Graveyard::new(2).inter_handle(vk::SwapchainKHR::from_raw(1));
+    }
+}
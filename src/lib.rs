@@ -2,6 +2,7 @@
 
 pub mod graveyard;
 pub mod parallel_queue;
+pub mod queue_families;
 pub mod staging_ring;
 
 mod memory;
@@ -10,12 +11,15 @@ mod ring_state;
 mod timeline_ring;
 mod visit_handles;
 
-pub use graveyard::{Graveyard, destroy_dynamic};
+pub use graveyard::{
+    Graveyard, GraveyardStats, SharedGraveyard, TimelineGraveyard, destroy_dynamic,
+};
 pub use memory::{
     AppendBuffer, DedicatedBuffer, DedicatedImage, DedicatedMapping, MemoryResource, ScratchBuffer,
     Staged, align, alloc_bind, find_memory_type,
 };
 pub use parallel_queue::ParallelQueue;
+pub use queue_families::{QueueFamilies, Queues};
 pub use region::{BufferRegion, BufferRegionAlloc, ImageRegion};
 pub use staging_ring::StagingRing;
 pub use timeline_ring::TimelineRing;
@@ -1,16 +1,32 @@
 use std::{
-    cell::RefCell,
-    collections::{BinaryHeap, VecDeque},
-    mem::ManuallyDrop,
-    num::NonZeroU64,
+    backtrace::{Backtrace, BacktraceStatus},
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BinaryHeap, VecDeque},
+    ffi::{CStr, CString},
+    fmt,
+    future::Future,
+    marker::PhantomData,
+    mem::{self, ManuallyDrop},
+    num::{NonZeroU64, NonZeroUsize},
+    ops::{Deref, Range},
+    pin::Pin,
+    ptr,
     sync::{
-        Arc,
-        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard, OnceLock,
+        atomic::{AtomicBool, AtomicU64, Ordering},
         mpsc,
     },
+    task::{Context, Poll, Waker},
+    thread,
+    time::{Duration, Instant},
 };
 
-use ash::{Device, ext, vk};
+use ash::{Device, ext, prelude::VkResult, vk};
+
+use crate::{
+    HandleVisitor, VisitHandles,
+    graveyard::{can_destroy_dynamic, destroy_dynamic},
+};
 
 pub struct ParallelQueue {
     shared: Arc<Shared>,
@@ -20,12 +36,27 @@ pub struct ParallelQueue {
     /// The lowest value not yet reached by the semaphore
     first_unsignaled: u64,
     pending: BinaryHeap<Message>,
-    queue: vk::Queue,
+    queues: Box<[vk::Queue]>,
+    /// Index into `queues` of the queue to submit to next
+    next_queue: usize,
     debug: Option<DebugLabelContext>,
+    batch_policy: BatchPolicy,
+    /// Union of the signal stage masks of all work submitted so far
+    ///
+    /// Signals with different stage masks could complete out of order, so each signal must cover
+    /// every stage used by earlier work.
+    signal_stage_mask: vk::PipelineStageFlags2,
+    profiling: Option<Profiling>,
+    /// Host callbacks awaiting completion of work, in timeline order
+    callbacks: VecDeque<(u64, Callback)>,
+    /// Handles to destroy once the semaphore reaches the associated time, in timeline order
+    retired: VecDeque<(u64, vk::ObjectType, u64)>,
 }
 
 impl ParallelQueue {
     /// # Safety
+    /// - `device` must support Vulkan 1.3, and have been created with the `timelineSemaphore` and
+    ///   `synchronization2` features enabled
     /// - `queue_family_index` must be associated with `queue` under `device`
     /// - `debug_utils` must be associated with `device`
     pub unsafe fn new(
@@ -34,6 +65,30 @@ impl ParallelQueue {
         queue: vk::Queue,
         debug_utils: Option<&ext::debug_utils::Device>,
     ) -> Self {
+        unsafe { Self::new_multi(device, queue_family_index, &[queue], debug_utils) }
+    }
+
+    /// Construct a queue that spreads submissions across several `vk::Queue`s of one family
+    ///
+    /// Submissions are issued to each queue in turn, so independent work may execute concurrently
+    /// if the implementation allows. The timeline is still signaled in order.
+    ///
+    /// Unlike with a single queue, work is **not** implicitly ordered after previously submitted
+    /// work, even work from the same [`Handle::begin_many`] call. Work that depends on earlier work
+    /// must declare it with [`Work::wait_for`].
+    ///
+    /// # Safety
+    /// - `device` must meet the requirements of [`new`](Self::new)
+    /// - `queue_family_index` must be associated with each of `queues` under `device`
+    /// - `queues` must be distinct and non-empty
+    /// - `debug_utils` must be associated with `device`
+    pub unsafe fn new_multi(
+        device: &Device,
+        queue_family_index: u32,
+        queues: &[vk::Queue],
+        debug_utils: Option<&ext::debug_utils::Device>,
+    ) -> Self {
+        assert!(!queues.is_empty(), "at least one queue is required");
         unsafe {
             let (send, recv) = mpsc::channel();
             let semaphore = device
@@ -50,6 +105,16 @@ impl ParallelQueue {
                 first_unallocated: AtomicU64::new(1),
                 semaphore,
                 send,
+                complete: AtomicU64::new(0),
+                wakers: Mutex::new(BTreeMap::new()),
+                wake: OnceLock::new(),
+                device_lost: AtomicBool::new(false),
+                debug_utils: debug_utils.cloned(),
+                profiler: OnceLock::new(),
+                first_unsubmitted: AtomicU64::new(1),
+                diagnostics: AtomicBool::new(false),
+                allocations: Mutex::new(BTreeMap::new()),
+                assign_on_end: AtomicBool::new(false),
             });
             Self {
                 shared,
@@ -57,18 +122,35 @@ impl ParallelQueue {
                 first_unsubmitted: 1,
                 first_unsignaled: 1,
                 pending: BinaryHeap::new(),
-                queue,
+                queues: queues.into(),
+                next_queue: 0,
                 debug: debug_utils.map(|x| DebugLabelContext::new(device, x, queue_family_index)),
+                batch_policy: BatchPolicy::default(),
+                signal_stage_mask: vk::PipelineStageFlags2::NONE,
+                profiling: None,
+                callbacks: VecDeque::new(),
+                retired: VecDeque::new(),
             }
         }
     }
 
+    /// Destroys any handles passed to [`Work::keep_alive`] by submitted work.
+    ///
     /// # Safety
     /// `device` must match that passed to `new` and no work may be in flight, as determined by
     /// calling `drain` after all work has been submitted.
     pub unsafe fn destroy(&mut self, device: &Device) {
         unsafe {
+            for (_, ty, handle) in self.retired.drain(..) {
+                destroy_dynamic(device, ty, handle);
+            }
             device.destroy_semaphore(self.shared.semaphore, None);
+            if let Some(wake) = self.shared.wake.get() {
+                device.destroy_semaphore(wake.semaphore, None);
+            }
+            if let Some(profiler) = self.shared.profiler.get() {
+                device.destroy_query_pool(profiler.pool, None);
+            }
             self.debug.as_mut().map(|x| x.destroy(device));
         }
     }
@@ -78,54 +160,277 @@ impl ParallelQueue {
         self.shared.semaphore
     }
 
+    /// Identifies this queue for cross-queue dependencies
+    #[inline]
+    pub fn timeline(&self) -> Timeline {
+        self.shared.timeline()
+    }
+
+    /// Whether a Vulkan call on this queue or any of its [`Handle`]s has reported
+    /// `VK_ERROR_DEVICE_LOST`
+    #[inline]
+    pub fn is_device_lost(&self) -> bool {
+        self.shared.is_device_lost()
+    }
+
+    /// Enable or disable recording where each [`Work`] was begun, for use by [`stall`](Self::stall)
+    ///
+    /// Records the name of the beginning thread, and a backtrace if enabled by `RUST_BACKTRACE`
+    /// or `RUST_LIB_BACKTRACE`. Only affects work begun afterwards.
+    pub fn set_diagnostics(&self, enabled: bool) {
+        self.shared.set_diagnostics(enabled);
+    }
+
+    /// The work, if any, that was begun but not yet submitted and is holding back later work
+    ///
+    /// Requires [`set_diagnostics`](Self::set_diagnostics). Reflects the queue as of the last
+    /// [`drive`](Self::drive), so work ended since then may still be reported.
+    pub fn stall(&self) -> Option<Stall> {
+        self.shared.stall()
+    }
+
     /// # Safety
     /// `device` must match that passed to `new`
     pub unsafe fn drive(&mut self, device: &Device) {
+        unsafe { self.try_drive(device).unwrap() }
+    }
+
+    /// Fallible version of [`drive`](Self::drive)
+    ///
+    /// If submission fails, work that wasn't submitted is retained, to be retried by the next call.
+    ///
+    /// # Safety
+    /// `device` must match that passed to `new`
+    pub unsafe fn try_drive(&mut self, device: &Device) -> VkResult<()> {
         unsafe {
+            self.shared.check_device()?;
+            if self.first_unsignaled < self.first_unsubmitted {
+                let complete = self
+                    .shared
+                    .check(device.get_semaphore_counter_value(self.shared.semaphore))?;
+                self.signaled(device, complete);
+                self.resolve_timings(device)?;
+            }
             while let Ok(work) = self.recv.try_recv() {
                 self.pending.push(work);
             }
-            let mut cmds = Vec::new();
+            let first_unsubmitted = self.first_unsubmitted;
+            let mut run = Vec::new();
             // Queue up the next contiguous run of work. By mandating that work be submitted in order
             // without gaps, we make the semaphore counter value a reliable indicator of when a work
             // item's execution is complete.
-            while self
-                .pending
-                .peek()
-                .map_or(false, |work| work.time().get() == self.first_unsubmitted)
-            {
-                if let Message::Execute(work) = self.pending.pop().unwrap() {
-                    if let Some(debug) = &self.debug
-                        && cmds.is_empty()
-                    {
-                        cmds.push(debug.begin);
-                    }
-                    cmds.push(work.cmd);
+            while self.pending.peek().map_or(false, |work| {
+                work.time().get() == first_unsubmitted + run.len() as u64
+            }) {
+                run.push(self.pending.pop().unwrap());
+            }
+            if run.is_empty() {
+                return Ok(());
+            }
+            for message in &run {
+                if let Message::Execute(submission) = message {
+                    self.signal_stage_mask |= submission.signal_stage_mask;
                 }
-                self.first_unsubmitted += 1;
             }
-            if cmds.is_empty() {
-                return;
+            let slots = run
+                .iter()
+                .map(|message| match message {
+                    Message::Execute(submission) => Slot::Execute {
+                        waits: !submission.waits.is_empty(),
+                    },
+                    Message::Reset(..) => Slot::Reset,
+                })
+                .collect::<Vec<_>>();
+            let calls = plan(self.batch_policy, first_unsubmitted, &slots);
+            let labeled = self.debug.is_some() && slots.iter().any(|x| *x != Slot::Reset);
+            let multi_queue = self.queues.len() > 1;
+            for call in calls {
+                let call_slots = call[0].slots.start..call.last().unwrap().slots.end;
+                let call_start = first_unsubmitted + call_slots.start as u64;
+                let mut batches = call
+                    .iter()
+                    .map(|planned| {
+                        let mut batch = Batch::default();
+                        for message in &run[planned.slots.clone()] {
+                            let Message::Execute(submission) = message else {
+                                continue;
+                            };
+                            // Only the first work in a batch may wait, by construction
+                            batch.waits.extend(submission.waits.iter().map(Wait::info));
+                            batch.cmds.push(
+                                vk::CommandBufferSubmitInfo::default()
+                                    .command_buffer(submission.work.cmd),
+                            );
+                        }
+                        batch.signal = planned.signal.map(|value| {
+                            vk::SemaphoreSubmitInfo::default()
+                                .semaphore(self.shared.semaphore)
+                                .value(value)
+                                .stage_mask(self.signal_stage_mask)
+                        });
+                        batch
+                    })
+                    .collect::<Vec<_>>();
+                // Wrap each call in a label of its own, so a failed submission can't leave one open
+                if let Some(debug) = &self.debug
+                    && labeled
+                {
+                    batches.first_mut().unwrap().cmds.insert(
+                        0,
+                        vk::CommandBufferSubmitInfo::default().command_buffer(debug.begin),
+                    );
+                    batches
+                        .last_mut()
+                        .unwrap()
+                        .cmds
+                        .push(vk::CommandBufferSubmitInfo::default().command_buffer(debug.end));
+                }
+                // Every signal of a call must wait for the value signaled by the previous call,
+                // unless that's already been reached
+                if multi_queue && call_start > self.first_unsignaled {
+                    gate_signals(&mut batches, self.shared.semaphore, call_start - 1);
+                }
+                let submits = batches
+                    .iter()
+                    .map(|batch| {
+                        vk::SubmitInfo2::default()
+                            .wait_semaphore_infos(&batch.waits)
+                            .command_buffer_infos(&batch.cmds)
+                            .signal_semaphore_infos(batch.signal.as_slice())
+                    })
+                    .collect::<Vec<_>>();
+                let queue = self.queues[self.next_queue];
+                let result =
+                    self.shared
+                        .check(device.queue_submit2(queue, &submits, vk::Fence::null()));
+                if let Err(e) = result {
+                    // A failed submission has no effect, so the rest of the run can be retried
+                    self.pending.extend(run.drain(call_slots.start..));
+                    return Err(e);
+                }
+                self.next_queue = (self.next_queue + 1) % self.queues.len();
+                self.submitted(&mut run[call_slots]);
+            }
+            Ok(())
+        }
+    }
+
+    /// Take charge of the callbacks, retired handles, and profiling of the submitted `messages`,
+    /// which must immediately follow all previously submitted work
+    fn submitted(&mut self, messages: &mut [Message]) {
+        for message in messages {
+            let time = message.time();
+            let retired = match message {
+                Message::Execute(submission) => &mut submission.retired,
+                Message::Reset(_, retired) => retired,
+            };
+            self.retired.extend(
+                retired
+                    .drain(..)
+                    .map(|(ty, handle)| (time.get(), ty, handle)),
+            );
+            if let Message::Execute(submission) = message {
+                if let Some((profiling, profiled)) =
+                    self.profiling.as_mut().zip(submission.profile.take())
+                {
+                    profiling.pending.push_back((time, profiled));
+                }
+                self.callbacks.extend(
+                    mem::take(&mut submission.callbacks)
+                        .into_iter()
+                        .map(|f| (time.get(), f)),
+                );
             }
-            if let Some(debug) = &self.debug {
-                cmds.push(debug.end);
+            debug_assert_eq!(time.get(), self.first_unsubmitted);
+            self.first_unsubmitted += 1;
+        }
+        self.shared.submitted(self.first_unsubmitted);
+        self.publish_profiling_progress();
+    }
+
+    /// Open a queue debug label, enclosing all work submitted until the matching
+    /// [`end_label`](Self::end_label)
+    ///
+    /// Labels are applied to every queue passed to [`new_multi`](Self::new_multi). No-op if no
+    /// `debug_utils` was passed to `new`.
+    pub fn begin_label(&mut self, name: &CStr, color: [f32; 4]) {
+        if let Some(debug_utils) = &self.shared.debug_utils {
+            for &queue in &self.queues {
+                // Safety: `&mut self` guarantees synchronized access to the queue
+                unsafe {
+                    debug_utils.queue_begin_debug_utils_label(
+                        queue,
+                        &vk::DebugUtilsLabelEXT::default()
+                            .label_name(name)
+                            .color(color),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Close the queue debug label most recently opened by [`begin_label`](Self::begin_label)
+    ///
+    /// # Safety
+    /// There must be an open label
+    pub unsafe fn end_label(&mut self) {
+        if let Some(debug_utils) = &self.shared.debug_utils {
+            for &queue in &self.queues {
+                unsafe {
+                    debug_utils.queue_end_debug_utils_label(queue);
+                }
+            }
+        }
+    }
+
+    /// Mark the point between work submitted before and after this call with a debug label
+    ///
+    /// No-op if no `debug_utils` was passed to `new`.
+    pub fn insert_label(&mut self, name: &CStr, color: [f32; 4]) {
+        if let Some(debug_utils) = &self.shared.debug_utils {
+            for &queue in &self.queues {
+                // Safety: `&mut self` guarantees synchronized access to the queue
+                unsafe {
+                    debug_utils.queue_insert_debug_utils_label(
+                        queue,
+                        &vk::DebugUtilsLabelEXT::default()
+                            .label_name(name)
+                            .color(color),
+                    );
+                }
             }
-            device
-                .queue_submit(
-                    self.queue,
-                    &[vk::SubmitInfo::default()
-                        .command_buffers(&cmds)
-                        .signal_semaphores(&[self.shared.semaphore])
-                        .push_next(
-                            &mut vk::TimelineSemaphoreSubmitInfo::default()
-                                .signal_semaphore_values(&[self.first_unsubmitted - 1]),
-                        )],
-                    vk::Fence::null(),
-                )
-                .unwrap();
         }
     }
 
+    /// Select when [`Work`] is assigned its time, and hence the order in which it's submitted
+    ///
+    /// Defaults to [`SubmissionOrder::Begin`]. Only affects work begun afterwards.
+    pub fn set_submission_order(&mut self, order: SubmissionOrder) {
+        self.shared
+            .assign_on_end
+            .store(order == SubmissionOrder::End, Ordering::Relaxed);
+    }
+
+    pub fn submission_order(&self) -> SubmissionOrder {
+        if self.shared.assign_on_end.load(Ordering::Relaxed) {
+            SubmissionOrder::End
+        } else {
+            SubmissionOrder::Begin
+        }
+    }
+
+    /// Select how [`drive`](Self::drive) groups work into submissions
+    ///
+    /// Defaults to [`BatchPolicy::Unbounded`].
+    pub fn set_batch_policy(&mut self, policy: BatchPolicy) {
+        self.batch_policy = policy;
+    }
+
+    #[inline]
+    pub fn batch_policy(&self) -> BatchPolicy {
+        self.batch_policy
+    }
+
     /// Wait until a submission is complete or `wake` reaches `wake_value`, returning the current
     /// timeline value
     ///
@@ -137,20 +442,36 @@ impl ParallelQueue {
     /// `device`
     #[inline]
     pub unsafe fn park(&mut self, device: &Device, wake: vk::Semaphore, wake_value: u64) -> u64 {
+        unsafe { self.try_park(device, wake, wake_value).unwrap() }
+    }
+
+    /// Fallible version of [`park`](Self::park)
+    ///
+    /// # Safety
+    ///
+    /// `device` must match that passed to `new`, and `wake` must be a valid timeline semaphore from
+    /// `device`
+    pub unsafe fn try_park(
+        &mut self,
+        device: &Device,
+        wake: vk::Semaphore,
+        wake_value: u64,
+    ) -> VkResult<u64> {
         unsafe {
-            device
-                .wait_semaphores(
-                    &vk::SemaphoreWaitInfo::default()
-                        .semaphores(&[self.shared.semaphore, wake])
-                        .values(&[self.first_unsignaled, wake_value]),
-                    !0,
-                )
-                .unwrap();
-            let complete = device
-                .get_semaphore_counter_value(self.shared.semaphore)
-                .unwrap();
-            self.first_unsignaled = complete + 1;
-            complete
+            self.shared.check_device()?;
+            self.shared.check(device.wait_semaphores(
+                &park_wait_info(
+                    &[self.shared.semaphore, wake],
+                    &[self.first_unsignaled, wake_value],
+                ),
+                !0,
+            ))?;
+            let complete = self
+                .shared
+                .check(device.get_semaphore_counter_value(self.shared.semaphore))?;
+            self.signaled(device, complete);
+            self.resolve_timings(device)?;
+            Ok(complete)
         }
     }
 
@@ -163,16 +484,145 @@ impl ParallelQueue {
     /// `device` must match that passed to `new`
     #[inline]
     pub unsafe fn drain(&mut self, device: &Device) {
+        unsafe { self.try_drain(device).unwrap() }
+    }
+
+    /// Fallible version of [`drain`](Self::drain)
+    ///
+    /// Once the device is lost, no work will ever complete, so it is safe to proceed with
+    /// destruction after this fails with `VK_ERROR_DEVICE_LOST`.
+    ///
+    /// # Safety
+    ///
+    /// `device` must match that passed to `new`
+    pub unsafe fn try_drain(&mut self, device: &Device) -> VkResult<()> {
         unsafe {
-            device
-                .wait_semaphores(
+            self.shared.check_device()?;
+            self.shared.check(
+                device.wait_semaphores(
                     &vk::SemaphoreWaitInfo::default()
                         .semaphores(&[self.shared.semaphore])
                         .values(&[self.first_unsubmitted - 1]),
                     !0,
-                )
-                .unwrap();
-            self.first_unsignaled = self.first_unsubmitted;
+                ),
+            )?;
+            self.signaled(device, self.first_unsubmitted - 1);
+            self.resolve_timings(device)
+        }
+    }
+
+    /// Begin measuring the GPU execution time of each [`Work`]
+    ///
+    /// Timestamps are written at the start and end of each work's command buffer into a ring of
+    /// `capacity` query slots. Work begun while every slot is awaiting resolution is not measured.
+    /// Results are collected by [`take_timings`](Self::take_timings) once the work completes.
+    /// `timestamp_period` and `timestamp_valid_bits` come from `VkPhysicalDeviceLimits` and this
+    /// queue's `VkQueueFamilyProperties`, respectively. Has no effect if already enabled.
+    ///
+    /// Query results are always read from `device`, so there is no way to drive this with a mock
+    /// device. Code consuming timings can instead be tested with
+    /// [`WorkTiming::from_timestamps`], which performs the same conversion from raw results.
+    ///
+    /// # Safety
+    /// `device` must match that passed to `new`, and the queue family must support timestamps
+    pub unsafe fn enable_profiling(
+        &mut self,
+        device: &Device,
+        timestamp_period: f32,
+        timestamp_valid_bits: u32,
+        capacity: u32,
+    ) -> VkResult<()> {
+        assert!(capacity > 0);
+        if self.profiling.is_some() {
+            return Ok(());
+        }
+        unsafe {
+            let pool = self.shared.check(
+                device.create_query_pool(
+                    &vk::QueryPoolCreateInfo::default()
+                        .query_type(vk::QueryType::TIMESTAMP)
+                        .query_count(2 * capacity),
+                    None,
+                ),
+            )?;
+            self.shared.profiler.get_or_init(|| Profiler {
+                pool,
+                capacity,
+                first_unresolved: AtomicU64::new(self.first_unsubmitted),
+            });
+        }
+        self.profiling = Some(Profiling::new(timestamp_period, timestamp_valid_bits));
+        Ok(())
+    }
+
+    /// Take the timings of all profiled work that has completed since the last call
+    ///
+    /// Always empty unless [`enable_profiling`](Self::enable_profiling) has been called.
+    pub fn take_timings(&mut self) -> Vec<WorkTiming> {
+        self.profiling
+            .as_mut()
+            .map(|x| mem::take(&mut x.timings))
+            .unwrap_or_default()
+    }
+
+    /// Read back timestamps from profiled work that has completed
+    unsafe fn resolve_timings(&mut self, device: &Device) -> VkResult<()> {
+        let (Some(profiling), Some(profiler)) = (&mut self.profiling, self.shared.profiler.get())
+        else {
+            return Ok(());
+        };
+        profiling.resolve(self.first_unsignaled - 1, |slot| {
+            let mut data = [0u64; 2];
+            unsafe {
+                self.shared.check(device.get_query_pool_results(
+                    profiler.pool,
+                    2 * slot,
+                    &mut data,
+                    vk::QueryResultFlags::TYPE_64,
+                ))?;
+            }
+            Ok(data)
+        })?;
+        self.publish_profiling_progress();
+        Ok(())
+    }
+
+    /// Allow handles to reuse query slots that are no longer needed
+    fn publish_profiling_progress(&self) {
+        let (Some(profiling), Some(profiler)) = (&self.profiling, self.shared.profiler.get())
+        else {
+            return;
+        };
+        profiler.first_unresolved.store(
+            profiling.first_unresolved(self.first_unsubmitted),
+            Ordering::Release,
+        );
+    }
+
+    /// Record that the semaphore has reached `complete`, waking any interested [`Completion`]s,
+    /// destroying retired handles, and running callbacks
+    ///
+    /// # Safety
+    /// `device` must match that passed to `new`
+    unsafe fn signaled(&mut self, device: &Device, complete: u64) {
+        self.first_unsignaled = complete + 1;
+        self.shared.complete.fetch_max(complete, Ordering::Release);
+        self.shared.wake_completions(Some(complete));
+        while let Some(&(time, ty, handle)) = self.retired.front()
+            && time <= complete
+        {
+            self.retired.pop_front();
+            unsafe {
+                destroy_dynamic(device, ty, handle);
+            }
+        }
+        while self
+            .callbacks
+            .front()
+            .is_some_and(|&(time, _)| time <= complete)
+        {
+            let (_, f) = self.callbacks.pop_front().unwrap();
+            f();
         }
     }
 
@@ -181,6 +631,145 @@ impl ParallelQueue {
     pub unsafe fn handle(&self, device: &Device) -> Handle {
         unsafe { self.shared.handle(device) }
     }
+
+    /// Create an empty pool of handles to share among worker threads
+    pub fn handle_pool(&self) -> HandlePool {
+        HandlePool::new(self.shared.clone())
+    }
+
+    /// Drive the queue on a dedicated background thread until [`DriverHandle::shutdown`]
+    ///
+    /// The thread sleeps until work completes or new work is ended or dropped.
+    ///
+    /// # Safety
+    /// `device` must match that passed to `new`
+    pub unsafe fn spawn_driver(self, device: Device) -> DriverHandle {
+        unsafe {
+            self.shared.wake.get_or_init(|| Wake {
+                semaphore: device
+                    .create_semaphore(
+                        &vk::SemaphoreCreateInfo::default().push_next(
+                            &mut vk::SemaphoreTypeCreateInfo::default()
+                                .semaphore_type(vk::SemaphoreType::TIMELINE),
+                        ),
+                        None,
+                    )
+                    .unwrap(),
+                value: Mutex::new(0),
+            });
+            let shared = self.shared.clone();
+            let shutdown = Arc::new(AtomicBool::new(false));
+            let thread = thread::Builder::new()
+                .name("lahar driver".into())
+                .spawn({
+                    let device = device.clone();
+                    let shutdown = shutdown.clone();
+                    let mut queue = self;
+                    move || {
+                        queue.run_driver(&device, &shutdown);
+                        queue
+                    }
+                })
+                .unwrap();
+            DriverHandle {
+                shared,
+                device,
+                shutdown,
+                thread,
+            }
+        }
+    }
+
+    /// # Safety
+    /// `device` must match that passed to `new`, and `spawn_driver` must have initialized the wake
+    /// semaphore
+    unsafe fn run_driver(&mut self, device: &Device, shutdown: &AtomicBool) {
+        unsafe {
+            let wake = self.shared.wake.get().unwrap().semaphore;
+            let Ok(mut woken) = self.shared.check(device.get_semaphore_counter_value(wake)) else {
+                return;
+            };
+            loop {
+                // Every message sent before the wake semaphore reached `woken` is received here
+                if self.try_drive(device).is_err() || shutdown.load(Ordering::Acquire) {
+                    break;
+                }
+                if self.try_park(device, wake, woken + 1).is_err() {
+                    break;
+                }
+                match self.shared.check(device.get_semaphore_counter_value(wake)) {
+                    Ok(x) => woken = x,
+                    Err(_) => break,
+                }
+            }
+            // Errors are exposed through `is_device_lost`
+            let _ = self.try_drain(device);
+        }
+    }
+}
+
+/// A [`ParallelQueue`] being driven on a background thread
+///
+/// Must be [`shutdown`](Self::shutdown) to recover the queue for destruction.
+pub struct DriverHandle {
+    shared: Arc<Shared>,
+    device: Device,
+    shutdown: Arc<AtomicBool>,
+    thread: thread::JoinHandle<ParallelQueue>,
+}
+
+impl DriverHandle {
+    #[inline]
+    pub fn semaphore(&self) -> vk::Semaphore {
+        self.shared.semaphore
+    }
+
+    /// Identifies the driven queue for cross-queue dependencies
+    #[inline]
+    pub fn timeline(&self) -> Timeline {
+        self.shared.timeline()
+    }
+
+    /// Create a handle for recording work onto the driven queue
+    pub fn handle(&self) -> Handle {
+        // Safety: `device` is the one passed to `spawn_driver`
+        unsafe { self.shared.handle(&self.device) }
+    }
+
+    /// Create an empty pool of handles to share among worker threads
+    pub fn handle_pool(&self) -> HandlePool {
+        HandlePool::new(self.shared.clone())
+    }
+
+    /// Whether the driver thread or any [`Handle`] has observed `VK_ERROR_DEVICE_LOST`
+    ///
+    /// The driver thread stops driving the queue after any error.
+    #[inline]
+    pub fn is_device_lost(&self) -> bool {
+        self.shared.is_device_lost()
+    }
+
+    /// See [`ParallelQueue::set_diagnostics`]
+    pub fn set_diagnostics(&self, enabled: bool) {
+        self.shared.set_diagnostics(enabled);
+    }
+
+    /// See [`ParallelQueue::stall`]
+    pub fn stall(&self) -> Option<Stall> {
+        self.shared.stall()
+    }
+
+    /// Stop the driver thread, returning the queue once all submitted work is complete
+    ///
+    /// Work that is still being recorded when this is called may never be submitted.
+    pub fn shutdown(self) -> ParallelQueue {
+        self.shutdown.store(true, Ordering::Release);
+        // Safety: `device` is the one passed to `spawn_driver`
+        unsafe {
+            self.shared.wake(&self.device);
+        }
+        self.thread.join().unwrap()
+    }
 }
 
 struct DebugLabelContext {
@@ -253,9 +842,151 @@ struct Shared {
     first_unallocated: AtomicU64,
     /// For cloning by handles.
     send: mpsc::Sender<Message>,
+    /// The highest value the semaphore has been observed to reach
+    complete: AtomicU64,
+    /// Tasks awaiting [`Completion`]s, keyed by the time they're waiting for
+    wakers: Mutex<BTreeMap<u64, Vec<Waker>>>,
+    /// Used to rouse the background thread, if any, when new messages are sent
+    wake: OnceLock<Wake>,
+    /// Whether `VK_ERROR_DEVICE_LOST` has been observed. Never reset.
+    device_lost: AtomicBool,
+    debug_utils: Option<ext::debug_utils::Device>,
+    profiler: OnceLock<Profiler>,
+    /// Published copy of [`ParallelQueue::first_unsubmitted`]
+    first_unsubmitted: AtomicU64,
+    /// Whether to record new entries in `allocations`
+    diagnostics: AtomicBool,
+    /// Where unsubmitted times were allocated, if diagnostics were enabled at the time
+    allocations: Mutex<BTreeMap<u64, Allocation>>,
+    /// Whether newly begun work is assigned a time when it's ended, per [`SubmissionOrder::End`]
+    assign_on_end: AtomicBool,
+}
+
+struct Wake {
+    semaphore: vk::Semaphore,
+    /// Guards signal operations, which must be strictly increasing
+    value: Mutex<u64>,
 }
 
 impl Shared {
+    /// Notify the driver thread, if any, of a new message
+    ///
+    /// # Safety
+    /// `device` must match that passed to [`ParallelQueue::new`]
+    unsafe fn wake(&self, device: &Device) {
+        let Some(wake) = self.wake.get() else {
+            return;
+        };
+        let mut value = wake.value.lock().unwrap();
+        *value += 1;
+        unsafe {
+            // Failure is exposed through `is_device_lost`, and the driver thread will stop anyway
+            let _ = self.check(
+                device.signal_semaphore(
+                    &vk::SemaphoreSignalInfo::default()
+                        .semaphore(wake.semaphore)
+                        .value(*value),
+                ),
+            );
+        }
+    }
+
+    fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Acquire)
+    }
+
+    /// Fail fast if the device has been lost
+    fn check_device(&self) -> VkResult<()> {
+        if self.is_device_lost() {
+            return Err(vk::Result::ERROR_DEVICE_LOST);
+        }
+        Ok(())
+    }
+
+    /// Pass through `result`, recording if it indicates that the device has been lost
+    fn check<T>(&self, result: VkResult<T>) -> VkResult<T> {
+        if let Err(vk::Result::ERROR_DEVICE_LOST) = result
+            && !self.device_lost.swap(true, Ordering::AcqRel)
+        {
+            // Nothing will ever complete now
+            self.wake_completions(None);
+        }
+        result
+    }
+
+    /// Wake [`Completion`]s waiting for times up to and including `complete`, or all of them if
+    /// `None`
+    fn wake_completions(&self, complete: Option<u64>) {
+        let woken = {
+            let mut wakers = self.wakers.lock().unwrap();
+            let pending = match complete {
+                Some(complete) => wakers.split_off(&(complete + 1)),
+                None => BTreeMap::new(),
+            };
+            mem::replace(&mut *wakers, pending)
+        };
+        for waker in woken.into_values().flatten() {
+            waker.wake();
+        }
+    }
+
+    fn timeline(&self) -> Timeline {
+        Timeline {
+            semaphore: self.semaphore,
+            queue_family_index: self.queue_family_index,
+        }
+    }
+
+    fn set_diagnostics(&self, enabled: bool) {
+        self.diagnostics.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Record where `time` was allocated, if diagnostics are enabled
+    fn allocated(&self, time: u64) {
+        if !self.diagnostics.load(Ordering::Relaxed) {
+            return;
+        }
+        let current = thread::current();
+        let thread = match current.name() {
+            Some(name) => name.to_owned(),
+            None => format!("{:?}", current.id()),
+        };
+        let allocation = Allocation {
+            thread,
+            backtrace: Arc::new(Backtrace::capture()),
+            at: Instant::now(),
+        };
+        self.allocations.lock().unwrap().insert(time, allocation);
+    }
+
+    /// Note that all times before `first_unsubmitted` have been submitted
+    fn submitted(&self, first_unsubmitted: u64) {
+        self.first_unsubmitted
+            .store(first_unsubmitted, Ordering::Relaxed);
+        let mut allocations = self.allocations.lock().unwrap();
+        if allocations.is_empty() {
+            return;
+        }
+        *allocations = allocations.split_off(&first_unsubmitted);
+    }
+
+    fn stall(&self) -> Option<Stall> {
+        let time = self.first_unsubmitted.load(Ordering::Relaxed);
+        let first_unallocated = self.first_unallocated.load(Ordering::Relaxed);
+        if time >= first_unallocated {
+            return None;
+        }
+        let allocations = self.allocations.lock().unwrap();
+        let allocation = allocations.get(&time)?;
+        Some(Stall {
+            time: NonZeroU64::new(time).unwrap(),
+            blocked: first_unallocated - time - 1,
+            elapsed: allocation.at.elapsed(),
+            thread: allocation.thread.clone(),
+            backtrace: allocation.backtrace.clone(),
+        })
+    }
+
     /// # Safety
     /// `device` must match that passed to [`ParallelQueue::new`]
     unsafe fn handle(self: &Arc<Self>, device: &Device) -> Handle {
@@ -281,8 +1012,10 @@ impl Shared {
             Handle {
                 shared: self.clone(),
                 cmd_pool,
+                allocated: Cell::new(spare_cmds.len()),
                 spare_cmds: RefCell::new(spare_cmds),
                 in_flight: RefCell::default(),
+                secondaries: RefCell::default(),
             }
         }
     }
@@ -293,169 +1026,1773 @@ impl Shared {
 /// To ensure host synchronization of the command pool underlying `cmd`, commands must not be
 /// recorded to `cmd` outside the lifetime `'a`.
 pub struct Work<'a> {
-    inner: ErasedWork,
+    state: WorkState,
     // `cmd` is morally a pointer into `cmd_pool` in `Handle`, so this is needed for soundness as
     // well as convenience.
     handle: &'a Handle,
     device: &'a Device,
 }
 
-impl Work<'_> {
+impl<'a> Work<'a> {
+    fn new(
+        handle: &'a Handle,
+        device: &'a Device,
+        cmd: vk::CommandBuffer,
+        time: Option<NonZeroU64>,
+        profile: Option<Profiled>,
+    ) -> Self {
+        Self {
+            state: WorkState::new(cmd, time, profile),
+            handle,
+            device,
+        }
+    }
+
     /// Command buffer to record commands onto
     pub fn cmd(&self) -> vk::CommandBuffer {
-        self.inner.cmd
+        self.state.cmd
     }
 
     /// Value the timeline semaphore will reach when `cmd` has been executed
+    ///
+    /// # Panics
+    /// Under [`SubmissionOrder::End`], where the time is not known until [`end`](Self::end)
     pub fn time(&self) -> NonZeroU64 {
-        self.inner.time
+        self.state.time()
     }
 
-    /// Send recorded commands out for execution
-    pub fn end(self) {
-        let this = ManuallyDrop::new(self);
-        // Safety:
-        // - `device` is the same one passed to `begin`
-        // - Our lifetime guarantees synchronized access to the command pool behind `cmd`
-        unsafe {
-            this.device.end_command_buffer(this.inner.cmd).unwrap();
-            this.handle
-                .shared
-                .send
-                .send(Message::Execute(this.inner))
-                .unwrap();
-        }
+    /// Wait for `semaphore` to reach `value` before executing `stage_mask` stages of `cmd`
+    ///
+    /// `value` is ignored for binary semaphores. Work that waits is submitted in a batch of its
+    /// own, so its waits do not delay neighboring work.
+    ///
+    /// # Safety
+    /// - `semaphore` must be from the `device` passed to [`Handle::begin`], and must not be
+    ///   destroyed until this work is complete
+    /// - If `semaphore` is binary, it must have a pending signal operation that no other wait
+    ///   consumes
+    pub unsafe fn wait(
+        &mut self,
+        semaphore: vk::Semaphore,
+        value: u64,
+        stage_mask: vk::PipelineStageFlags2,
+    ) {
+        self.state.wait(semaphore, value, stage_mask);
     }
-}
 
-impl Drop for Work<'_> {
-    fn drop(&mut self) {
-        // Safety:
+    /// Limit the stages that must finish before the timeline semaphore signals this work complete
+    ///
+    /// Defaults to `ALL_COMMANDS`. `stage_mask` must include every stage in which commands recorded
+    /// to `cmd` execute, including the source stages of any [`release`](Self::release). Because
+    /// timeline signals must complete in order, narrowing only takes effect if all work on the
+    /// queue is similarly narrowed.
+    pub fn signal_stage_mask(&mut self, stage_mask: vk::PipelineStageFlags2) {
+        self.state.signal_stage_mask = stage_mask;
+    }
+
+    /// Destroy the handles in `resources` once this work is complete
+    ///
+    /// Destruction happens on the thread driving the queue, using [`destroy_dynamic`]. Takes effect
+    /// even if this work is dropped rather than [`end`](Self::end)ed.
+    ///
+    /// Only core object types are supported, since the queue has no extension loaders. Handles of
+    /// extension types such as `vk::SwapchainKHR` should instead be interred in a
+    /// [`TimelineGraveyard`](crate::TimelineGraveyard) constructed with the necessary
+    /// [`Extensions`](crate::graveyard::Extensions), waiting on this work's [`time`](Self::time).
+    ///
+    /// # Panics
+    /// If any resource's type is not supported, per [`can_destroy_dynamic`]
+    ///
+    /// # Safety
+    /// `resources` must not be used by any other work or host code after this work completes
+    pub unsafe fn keep_alive(&mut self, resources: impl VisitHandles) {
+        self.state.keep_alive(resources);
+    }
+
+    /// Run `f` on the thread driving the queue once this work is complete
+    ///
+    /// Callbacks run in timeline order from [`ParallelQueue::drive`], [`park`](ParallelQueue::park),
+    /// or [`drain`](ParallelQueue::drain), so they should be brief. Dropped if this work is dropped
+    /// rather than [`end`](Self::end)ed, or if the device is lost.
+    pub fn on_complete(&mut self, f: impl FnOnce() + Send + 'static) {
+        self.state.callbacks.push(Box::new(f));
+    }
+
+    /// Wait for work on another [`ParallelQueue`] to reach `time` before executing `stage_mask`
+    /// stages of `cmd`
+    ///
+    /// # Safety
+    /// `src` must be from a [`ParallelQueue`] on the `device` passed to [`Handle::begin`] that is
+    /// not destroyed until this work is complete
+    pub unsafe fn wait_for(
+        &mut self,
+        src: Timeline,
+        time: NonZeroU64,
+        stage_mask: vk::PipelineStageFlags2,
+    ) {
+        self.state.wait(src.semaphore, time.get(), stage_mask);
+    }
+
+    /// Record the release half of a queue family ownership transfer of `transfer` to `dst`
+    ///
+    /// `src_stage_mask` and `src_access_mask` describe the accesses that must complete before the
+    /// release. The `Work` on `dst` that uses the resources must call [`acquire`](Self::acquire)
+    /// with the same `transfer` and this work's [`time`](Self::time).
+    ///
+    /// # Safety
+    /// The resources in `transfer` must be owned by this queue's family
+    pub unsafe fn release(
+        &self,
+        dst: Timeline,
+        transfer: &OwnershipTransfer,
+        src_stage_mask: vk::PipelineStageFlags2,
+        src_access_mask: vk::AccessFlags2,
+    ) {
+        unsafe {
+            self.state.release(
+                self.handle,
+                self.device,
+                dst,
+                transfer,
+                src_stage_mask,
+                src_access_mask,
+            );
+        }
+    }
+
+    /// Wait for work on `src` to reach `time`, then record the acquire half of a queue family
+    /// ownership transfer of `transfer`
+    ///
+    /// `dst_stage_mask` and `dst_access_mask` describe the accesses that must wait for the
+    /// acquire.
+    ///
+    /// # Safety
+    /// - `src` must be from a [`ParallelQueue`] on the `device` passed to [`Handle::begin`] that is
+    ///   not destroyed until this work is complete
+    /// - The work on `src` at `time` must have called [`release`](Self::release) with an identical
+    ///   `transfer` and this queue as the destination
+    pub unsafe fn acquire(
+        &mut self,
+        src: Timeline,
+        time: NonZeroU64,
+        transfer: &OwnershipTransfer,
+        dst_stage_mask: vk::PipelineStageFlags2,
+        dst_access_mask: vk::AccessFlags2,
+    ) {
+        self.state.wait(src.semaphore, time.get(), dst_stage_mask);
+        unsafe {
+            self.state.acquire(
+                self.handle,
+                self.device,
+                src,
+                transfer,
+                dst_stage_mask,
+                dst_access_mask,
+            );
+        }
+    }
+
+    /// Record the execution of `secondaries` into `cmd`
+    ///
+    /// The secondary command buffers will not be reused until this work completes, or is dropped.
+    ///
+    /// # Safety
+    /// `secondaries` must be from handles to the same [`ParallelQueue`], and compatible with the
+    /// state of `cmd`
+    pub unsafe fn execute(&mut self, secondaries: impl IntoIterator<Item = RecordedSecondary>) {
+        unsafe {
+            self.state.execute(self.device, secondaries);
+        }
+    }
+
+    /// Send recorded commands out for execution
+    ///
+    /// The returned [`Completion`] may be awaited to learn when execution finishes, or simply
+    /// dropped. Its [`time`](Completion::time) is the work's time, which under
+    /// [`SubmissionOrder::End`] is assigned here.
+    pub fn end(self) -> Completion {
+        let handle = self.handle;
+        let device = self.device;
+        // Safety:
+        // - `device` is the same one passed to `begin`
+        // - Our lifetime guarantees synchronized access to the command pool behind `cmd`
+        unsafe { self.into_state().end(handle, device) }
+    }
+
+    /// Take responsibility for submitting or abandoning the work
+    fn into_state(self) -> WorkState {
+        let this = ManuallyDrop::new(self);
+        // Safety: `this` is never accessed or dropped again
+        unsafe { ptr::read(&this.state) }
+    }
+}
+
+impl Drop for Work<'_> {
+    fn drop(&mut self) {
+        // Safety:
         // - `device` is the same one passed to `begin`
         // - Our lifetime guarantees synchronized access to the command pool behind `cmd`
+        unsafe {
+            self.state.abandon(self.handle, self.device);
+        }
+    }
+}
+
+/// State of a work item being recorded, independent of how access to its [`Handle`] is
+/// synchronized
+///
+/// Methods that take a `handle` and `device` require that they are those the work was begun
+/// with, and that access to the handle's command pool is synchronized.
+struct WorkState {
+    cmd: vk::CommandBuffer,
+    /// `None` until `end` or drop under [`SubmissionOrder::End`]
+    time: Option<NonZeroU64>,
+    waits: Vec<Wait>,
+    /// Reuse times of executed secondary command buffers, to be assigned once ours is known
+    secondaries: Vec<Arc<AtomicU64>>,
+    /// Whether `cmd` has an open debug label
+    labeled: bool,
+    /// Where to write timestamps, if this work is being profiled
+    profile: Option<Profiled>,
+    callbacks: Vec<Callback>,
+    /// Handles passed to `keep_alive`
+    retired: Vec<(vk::ObjectType, u64)>,
+    signal_stage_mask: vk::PipelineStageFlags2,
+}
+
+impl WorkState {
+    fn new(cmd: vk::CommandBuffer, time: Option<NonZeroU64>, profile: Option<Profiled>) -> Self {
+        Self {
+            cmd,
+            time,
+            waits: Vec::new(),
+            secondaries: Vec::new(),
+            labeled: false,
+            profile,
+            callbacks: Vec::new(),
+            retired: Vec::new(),
+            signal_stage_mask: vk::PipelineStageFlags2::ALL_COMMANDS,
+        }
+    }
+
+    fn time(&self) -> NonZeroU64 {
+        self.time
+            .expect("time is assigned by `end` under `SubmissionOrder::End`")
+    }
+
+    /// Get our time, allocating it if necessary
+    fn assign_time(&mut self, handle: &Handle) -> NonZeroU64 {
+        *self.time.get_or_insert_with(|| {
+            let time = handle.allocate_times(1);
+            handle.in_flight.borrow_mut().push_back(ErasedWork {
+                cmd: self.cmd,
+                time,
+            });
+            time
+        })
+    }
+
+    fn wait(&mut self, semaphore: vk::Semaphore, value: u64, stage_mask: vk::PipelineStageFlags2) {
+        self.waits.push(Wait {
+            semaphore,
+            value,
+            stage_mask,
+        });
+    }
+
+    fn keep_alive(&mut self, resources: impl VisitHandles) {
+        struct Visitor<'a>(&'a mut Vec<(vk::ObjectType, u64)>);
+        impl HandleVisitor for Visitor<'_> {
+            fn visit_dynamic(&mut self, ty: vk::ObjectType, handle: u64) {
+                assert!(can_destroy_dynamic(ty), "cannot destroy {:?} handles", ty);
+                self.0.push((ty, handle));
+            }
+        }
+        resources.visit_handles(&mut Visitor(&mut self.retired));
+    }
+
+    unsafe fn release(
+        &self,
+        handle: &Handle,
+        device: &Device,
+        dst: Timeline,
+        transfer: &OwnershipTransfer,
+        src_stage_mask: vk::PipelineStageFlags2,
+        src_access_mask: vk::AccessFlags2,
+    ) {
+        let src_family = handle.shared.queue_family_index;
+        if src_family == dst.queue_family_index {
+            // Ownership is shared; `acquire` will perform any layout transitions
+            return;
+        }
+        unsafe {
+            transfer.record(
+                device,
+                self.cmd,
+                src_family,
+                dst.queue_family_index,
+                (src_stage_mask, src_access_mask),
+                (vk::PipelineStageFlags2::NONE, vk::AccessFlags2::NONE),
+            );
+        }
+    }
+
+    /// Record the acquire barrier, assuming a wait for `src` has already been added
+    unsafe fn acquire(
+        &self,
+        handle: &Handle,
+        device: &Device,
+        src: Timeline,
+        transfer: &OwnershipTransfer,
+        dst_stage_mask: vk::PipelineStageFlags2,
+        dst_access_mask: vk::AccessFlags2,
+    ) {
+        let dst_family = handle.shared.queue_family_index;
+        let (src_family, dst_family) = if src.queue_family_index == dst_family {
+            (vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED)
+        } else {
+            (src.queue_family_index, dst_family)
+        };
+        unsafe {
+            // The source stages chain with the semaphore wait
+            transfer.record(
+                device,
+                self.cmd,
+                src_family,
+                dst_family,
+                (dst_stage_mask, vk::AccessFlags2::NONE),
+                (dst_stage_mask, dst_access_mask),
+            );
+        }
+    }
+
+    unsafe fn execute(
+        &mut self,
+        device: &Device,
+        secondaries: impl IntoIterator<Item = RecordedSecondary>,
+    ) {
+        let mut cmds = Vec::new();
+        for secondary in secondaries {
+            cmds.push(secondary.cmd);
+            self.secondaries.push(secondary.into_time());
+        }
+        unsafe {
+            device.cmd_execute_commands(self.cmd, &cmds);
+        }
+    }
+
+    /// Allow executed secondary command buffers to be reused after this work's time
+    fn release_secondaries(&mut self, time: NonZeroU64) {
+        for secondary in mem::take(&mut self.secondaries) {
+            secondary.store(time.get(), Ordering::Release);
+        }
+    }
+
+    unsafe fn end(mut self, handle: &Handle, device: &Device) -> Completion {
+        let time = self.assign_time(handle);
+        self.release_secondaries(time);
+        unsafe {
+            if let Some(ref profiled) = self.profile {
+                let profiler = handle.shared.profiler.get().unwrap();
+                device.cmd_write_timestamp2(
+                    self.cmd,
+                    vk::PipelineStageFlags2::BOTTOM_OF_PIPE,
+                    profiler.pool,
+                    2 * profiled.slot + 1,
+                );
+            }
+            if self.labeled {
+                let debug_utils = handle.shared.debug_utils.as_ref().unwrap();
+                debug_utils.cmd_end_debug_utils_label(self.cmd);
+            }
+            device.end_command_buffer(self.cmd).unwrap();
+            handle
+                .shared
+                .send
+                .send(Message::Execute(Submission {
+                    work: ErasedWork {
+                        cmd: self.cmd,
+                        time,
+                    },
+                    waits: self.waits,
+                    signal_stage_mask: self.signal_stage_mask,
+                    profile: self.profile,
+                    callbacks: self.callbacks,
+                    retired: self.retired,
+                }))
+                .unwrap();
+            handle.shared.wake(device);
+        }
+        Completion {
+            shared: handle.shared.clone(),
+            time,
+        }
+    }
+
+    /// Return `cmd` unexecuted
+    unsafe fn abandon(&mut self, handle: &Handle, device: &Device) {
+        unsafe {
+            device
+                .reset_command_buffer(self.cmd, vk::CommandBufferResetFlags::empty())
+                .unwrap();
+            let time = self.assign_time(handle);
+            handle
+                .shared
+                .send
+                .send(Message::Reset(time, mem::take(&mut self.retired)))
+                .unwrap();
+            handle.shared.wake(device);
+            self.release_secondaries(time);
+        }
+    }
+}
+
+/// Future that resolves when the commands of an [`end`](Work::end)ed [`Work`] have finished
+/// executing
+///
+/// Completion is only observed by [`ParallelQueue::drive`], [`ParallelQueue::park`], and
+/// [`ParallelQueue::drain`], so the queue must continue to be driven for this to resolve. Resolves
+/// to `VK_ERROR_DEVICE_LOST` if the device is lost first.
+pub struct Completion {
+    shared: Arc<Shared>,
+    time: NonZeroU64,
+}
+
+impl Completion {
+    /// Value the timeline semaphore will reach when the work is complete
+    #[inline]
+    pub fn time(&self) -> NonZeroU64 {
+        self.time
+    }
+
+    /// Whether the work has been observed to be complete
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.shared.complete.load(Ordering::Acquire) >= self.time.get()
+    }
+
+    /// Whether the device was lost before the work was observed to complete
+    #[inline]
+    pub fn is_device_lost(&self) -> bool {
+        self.shared.is_device_lost()
+    }
+
+    fn status(&self) -> Poll<VkResult<()>> {
+        if self.is_complete() {
+            Poll::Ready(Ok(()))
+        } else if self.is_device_lost() {
+            Poll::Ready(Err(vk::Result::ERROR_DEVICE_LOST))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Future for Completion {
+    type Output = VkResult<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<VkResult<()>> {
+        if let Poll::Ready(result) = self.status() {
+            return Poll::Ready(result);
+        }
+        let mut wakers = self.shared.wakers.lock().unwrap();
+        // Check again while holding the lock, in case wakers were woken concurrently
+        if let Poll::Ready(result) = self.status() {
+            return Poll::Ready(result);
+        }
+        let wakers = wakers.entry(self.time.get()).or_default();
+        if !wakers.iter().any(|x| x.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+/// The timeline semaphore and queue family of a [`ParallelQueue`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Timeline {
+    pub semaphore: vk::Semaphore,
+    pub queue_family_index: u32,
+}
+
+/// Resources to be handed between [`ParallelQueue`]s by [`Work::release`] and [`Work::acquire`]
+#[derive(Default, Clone)]
+pub struct OwnershipTransfer {
+    buffers: Vec<BufferTransfer>,
+    images: Vec<ImageTransfer>,
+}
+
+impl OwnershipTransfer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Transfer `size` bytes of `buffer` starting at `offset`
+    pub fn buffer(
+        &mut self,
+        buffer: vk::Buffer,
+        offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+    ) -> &mut Self {
+        self.buffers.push(BufferTransfer {
+            buffer,
+            offset,
+            size,
+        });
+        self
+    }
+
+    /// Transfer `subresource_range` of `image`, transitioning it from `old_layout` to `new_layout`
+    pub fn image(
+        &mut self,
+        image: vk::Image,
+        subresource_range: vk::ImageSubresourceRange,
+        old_layout: vk::ImageLayout,
+        new_layout: vk::ImageLayout,
+    ) -> &mut Self {
+        self.images.push(ImageTransfer {
+            image,
+            subresource_range,
+            old_layout,
+            new_layout,
+        });
+        self
+    }
+
+    unsafe fn record(
+        &self,
+        device: &Device,
+        cmd: vk::CommandBuffer,
+        src_queue_family_index: u32,
+        dst_queue_family_index: u32,
+        (src_stage_mask, src_access_mask): (vk::PipelineStageFlags2, vk::AccessFlags2),
+        (dst_stage_mask, dst_access_mask): (vk::PipelineStageFlags2, vk::AccessFlags2),
+    ) {
+        let buffers = self
+            .buffers
+            .iter()
+            .map(|x| {
+                vk::BufferMemoryBarrier2::default()
+                    .src_stage_mask(src_stage_mask)
+                    .src_access_mask(src_access_mask)
+                    .dst_stage_mask(dst_stage_mask)
+                    .dst_access_mask(dst_access_mask)
+                    .src_queue_family_index(src_queue_family_index)
+                    .dst_queue_family_index(dst_queue_family_index)
+                    .buffer(x.buffer)
+                    .offset(x.offset)
+                    .size(x.size)
+            })
+            .collect::<Vec<_>>();
+        let images = self
+            .images
+            .iter()
+            .map(|x| {
+                vk::ImageMemoryBarrier2::default()
+                    .src_stage_mask(src_stage_mask)
+                    .src_access_mask(src_access_mask)
+                    .dst_stage_mask(dst_stage_mask)
+                    .dst_access_mask(dst_access_mask)
+                    .old_layout(x.old_layout)
+                    .new_layout(x.new_layout)
+                    .src_queue_family_index(src_queue_family_index)
+                    .dst_queue_family_index(dst_queue_family_index)
+                    .image(x.image)
+                    .subresource_range(x.subresource_range)
+            })
+            .collect::<Vec<_>>();
+        unsafe {
+            device.cmd_pipeline_barrier2(
+                cmd,
+                &vk::DependencyInfo::default()
+                    .buffer_memory_barriers(&buffers)
+                    .image_memory_barriers(&images),
+            );
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct BufferTransfer {
+    buffer: vk::Buffer,
+    offset: vk::DeviceSize,
+    size: vk::DeviceSize,
+}
+
+#[derive(Copy, Clone)]
+struct ImageTransfer {
+    image: vk::Image,
+    subresource_range: vk::ImageSubresourceRange,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+}
+
+/// A semaphore wait operation to perform before executing a [`Work`]
+#[derive(Copy, Clone)]
+struct Wait {
+    semaphore: vk::Semaphore,
+    value: u64,
+    stage_mask: vk::PipelineStageFlags2,
+}
+
+impl Wait {
+    fn info(&self) -> vk::SemaphoreSubmitInfo<'static> {
+        vk::SemaphoreSubmitInfo::default()
+            .semaphore(self.semaphore)
+            .value(self.value)
+            .stage_mask(self.stage_mask)
+    }
+}
+
+/// An ended [`Work`] and the context needed to submit it
+struct Submission {
+    work: ErasedWork,
+    waits: Vec<Wait>,
+    signal_stage_mask: vk::PipelineStageFlags2,
+    profile: Option<Profiled>,
+    callbacks: Vec<Callback>,
+    retired: Vec<(vk::ObjectType, u64)>,
+}
+
+/// Host code to run once work completes
+type Callback = Box<dyn FnOnce() + Send>;
+
+/// Command buffers to be submitted together, after the same semaphore waits
+#[derive(Default)]
+struct Batch {
+    waits: Vec<vk::SemaphoreSubmitInfo<'static>>,
+    cmds: Vec<vk::CommandBufferSubmitInfo<'static>>,
+    signal: Option<vk::SemaphoreSubmitInfo<'static>>,
+}
+
+/// When [`Work`] is assigned its [`time`](Work::time)
+///
+/// Either way, the timeline semaphore reaching a work's time means that work and all work with
+/// earlier times has finished.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum SubmissionOrder {
+    /// Assign times in [`Handle::begin`], submitting work in the order it was begun
+    ///
+    /// Work that takes a long time to record holds back submission of all work begun after it.
+    #[default]
+    Begin,
+    /// Assign times in [`Work::end`], submitting work as soon as it's ended
+    ///
+    /// The time is available only from the [`Completion`] returned by `end`. Profiling is not
+    /// supported.
+    End,
+}
+
+/// How [`ParallelQueue::drive`] groups ready work into submissions
+///
+/// Regardless of policy, work that waits on semaphores is placed in a batch of its own after a
+/// signal of all earlier work, and abandoned work never delays the timeline.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum BatchPolicy {
+    /// Submit all ready work at once, signaling the timeline only after the last item and before
+    /// any item that waits
+    ///
+    /// Minimizes submission overhead, but no item is observed complete until all are.
+    #[default]
+    Unbounded,
+    /// Submit each work item separately
+    PerWork,
+    /// Submit up to this many work items at a time, signaling the timeline after each submission
+    Limit(NonZeroUsize),
+    /// Submit all ready work at once, signaling the timeline after every item
+    SignalEach,
+}
+
+/// Submission-relevant properties of a message
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Slot {
+    Execute { waits: bool },
+    Reset,
+}
+
+/// A batch of consecutive slots
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct PlannedBatch {
+    slots: Range<usize>,
+    /// Timeline value to signal after the batch
+    signal: Option<u64>,
+}
+
+/// Wait for whichever of `semaphores` first reaches its value in `values`
+///
+/// Used by [`ParallelQueue::park`] with the queue's timeline and a wake semaphore. Waiting for both
+/// would deadlock a driver thread, since only the driver submits the work that advances the
+/// timeline.
+fn park_wait_info<'a>(
+    semaphores: &'a [vk::Semaphore; 2],
+    values: &'a [u64; 2],
+) -> vk::SemaphoreWaitInfo<'a> {
+    vk::SemaphoreWaitInfo::default()
+        .flags(vk::SemaphoreWaitFlags::ANY)
+        .semaphores(semaphores)
+        .values(values)
+}
+
+/// Group a run of `slots`, the first of which has time `first_time`, into queue submission calls
+/// made up of batches
+fn plan(policy: BatchPolicy, first_time: u64, slots: &[Slot]) -> Vec<Vec<PlannedBatch>> {
+    let mut calls = Vec::<Vec<PlannedBatch>>::new();
+    let mut call_executes = 0;
+    let mut batch_executes = 0;
+    let mut batch_waits = false;
+    for (i, &slot) in slots.iter().enumerate() {
+        let Slot::Execute { waits } = slot else {
+            // Abandoned work needs no submission of its own, just coverage by a signal
+            match calls.last_mut() {
+                Some(call) => call.last_mut().unwrap().slots.end = i + 1,
+                None => calls.push(vec![PlannedBatch {
+                    slots: i..i + 1,
+                    signal: None,
+                }]),
+            }
+            continue;
+        };
+        let call_full = match policy {
+            BatchPolicy::Unbounded | BatchPolicy::SignalEach => false,
+            BatchPolicy::PerWork => call_executes >= 1,
+            BatchPolicy::Limit(n) => call_executes >= n.get(),
+        };
+        if calls.is_empty() || call_full {
+            calls.push(Vec::new());
+            call_executes = 0;
+        }
+        let call = calls.last_mut().unwrap();
+        if call.is_empty()
+            || waits
+            || batch_waits
+            || (policy == BatchPolicy::SignalEach && batch_executes > 0)
+        {
+            call.push(PlannedBatch {
+                slots: i..i + 1,
+                signal: None,
+            });
+            batch_executes = 0;
+            batch_waits = waits;
+        } else {
+            call.last_mut().unwrap().slots.end = i + 1;
+        }
+        batch_executes += 1;
+        call_executes += 1;
+    }
+    for call in &mut calls {
+        let signal_each = policy == BatchPolicy::SignalEach;
+        for i in 0..call.len() {
+            // A wait may be on earlier work in the same call, e.g. a chain begun with
+            // `Handle::begin_many`, which must therefore be signaled first
+            let next_waits = call
+                .get(i + 1)
+                .is_some_and(|next| slots[next.slots.start] == Slot::Execute { waits: true });
+            if signal_each || i + 1 == call.len() || next_waits {
+                let batch = &mut call[i];
+                batch.signal = Some(first_time + batch.slots.end as u64 - 1);
+            }
+        }
+    }
+    calls
+}
+
+/// Move each signal in `batches` into a batch of its own that first waits for `semaphore` to reach
+/// `value`
+///
+/// Used when calls go to different queues, whose signals may otherwise complete out of order. A
+/// semaphore wait only orders work in its own batch, so the signal must move to the new batch,
+/// after the commands it covers.
+fn gate_signals(batches: &mut Vec<Batch>, semaphore: vk::Semaphore, value: u64) {
+    for i in (0..batches.len()).rev() {
+        let Some(signal) = batches[i].signal.take() else {
+            continue;
+        };
+        batches.insert(
+            i + 1,
+            Batch {
+                waits: vec![
+                    vk::SemaphoreSubmitInfo::default()
+                        .semaphore(semaphore)
+                        .value(value)
+                        .stage_mask(vk::PipelineStageFlags2::ALL_COMMANDS),
+                ],
+                cmds: Vec::new(),
+                signal: Some(signal),
+            },
+        );
+    }
+}
+
+#[derive(Copy, Clone)]
+struct ErasedWork {
+    cmd: vk::CommandBuffer,
+    time: NonZeroU64,
+}
+
+pub struct Handle {
+    shared: Arc<Shared>,
+    cmd_pool: vk::CommandPool,
+    /// Number of command buffers allocated from `cmd_pool`
+    allocated: Cell<usize>,
+    spare_cmds: RefCell<Vec<vk::CommandBuffer>>,
+    in_flight: RefCell<VecDeque<ErasedWork>>,
+    secondaries: RefCell<SecondaryCmds>,
+}
+
+/// Secondary command buffers owned by a [`Handle`]
+#[derive(Default)]
+struct SecondaryCmds {
+    spare: Vec<vk::CommandBuffer>,
+    /// Command buffers and the times after which they may be reused, in order of allocation
+    in_flight: VecDeque<(vk::CommandBuffer, Arc<AtomicU64>)>,
+}
+
+/// Placeholder time of a secondary command buffer that isn't yet associated with a [`Work`]
+const UNASSIGNED: u64 = u64::MAX;
+/// Time of a secondary command buffer that may be reused immediately
+const ABANDONED: u64 = 0;
+
+impl Handle {
+    /// # Safety
+    /// `device` must match that passed to [`ParallelQueue::new`] and no work may be in flight
+    pub unsafe fn destroy(&mut self, device: &Device) {
+        unsafe {
+            device.destroy_command_pool(self.cmd_pool, None);
+        }
+    }
+
+    #[inline]
+    pub fn semaphore(&self) -> vk::Semaphore {
+        self.shared.semaphore
+    }
+
+    /// Identifies the underlying [`ParallelQueue`] for cross-queue dependencies
+    #[inline]
+    pub fn timeline(&self) -> Timeline {
+        self.shared.timeline()
+    }
+
+    /// Whether `VK_ERROR_DEVICE_LOST` has been observed by the [`ParallelQueue`] or any of its
+    /// handles
+    #[inline]
+    pub fn is_device_lost(&self) -> bool {
+        self.shared.is_device_lost()
+    }
+
+    #[inline]
+    pub fn cmd_pool(&self) -> vk::CommandPool {
+        self.cmd_pool
+    }
+
+    /// Obtain a command buffer that commands may be recorded into
+    ///
+    /// The [`Work`] must be dropped or [`end`](Work::end)ed soon to avoid stalling the queue.
+    ///
+    /// # Safety
+    /// - `device` must match that passed to [`ParallelQueue::new`]
+    /// - [`Work::cmd`] must not be used outside the lifetime of the returned [`Work`]
+    pub unsafe fn begin<'a>(&'a self, device: &'a Device) -> Work<'a> {
+        unsafe { self.try_begin(device).unwrap() }
+    }
+
+    /// Like [`begin`](Self::begin), but wraps the work's commands in a debug label
+    ///
+    /// The label is nested inside the label enclosing each batch. Equivalent to `begin` if no
+    /// `debug_utils` was passed to [`ParallelQueue::new`].
+    ///
+    /// # Safety
+    /// - `device` must match that passed to [`ParallelQueue::new`]
+    /// - [`Work::cmd`] must not be used outside the lifetime of the returned [`Work`]
+    pub unsafe fn begin_labeled<'a>(
+        &'a self,
+        device: &'a Device,
+        name: &CStr,
+        color: [f32; 4],
+    ) -> Work<'a> {
+        unsafe {
+            let mut work = self.begin(device);
+            if let Some(debug_utils) = &self.shared.debug_utils {
+                debug_utils.cmd_begin_debug_utils_label(
+                    work.state.cmd,
+                    &vk::DebugUtilsLabelEXT::default()
+                        .label_name(name)
+                        .color(color),
+                );
+                work.state.labeled = true;
+            }
+            if let Some(profiled) = &mut work.state.profile {
+                profiled.label = Some(name.to_owned());
+            }
+            work
+        }
+    }
+
+    /// Fallible version of [`begin`](Self::begin)
+    ///
+    /// Fails fast with `VK_ERROR_DEVICE_LOST` if the device has been lost.
+    ///
+    /// # Safety
+    /// - `device` must match that passed to [`ParallelQueue::new`]
+    /// - [`Work::cmd`] must not be used outside the lifetime of the returned [`Work`]
+    pub unsafe fn try_begin<'a>(&'a self, device: &'a Device) -> VkResult<Work<'a>> {
+        self.shared.check_device()?;
+        unsafe {
+            let cmd = self.begin_cmd(device)?;
+            if self.shared.assign_on_end.load(Ordering::Relaxed) {
+                return Ok(Work::new(self, device, cmd, None, None));
+            }
+            // Allocate a time only once nothing can fail, since every time must be submitted
+            let time = self.allocate_times(1);
+            Ok(self.start(device, cmd, time))
+        }
+    }
+
+    /// Obtain `n` command buffers whose work will be assigned consecutive times
+    ///
+    /// Useful for chains of dependent submissions whose completion times must be known up front,
+    /// without work from other threads interleaved. Unused [`Work`]s should be dropped promptly, as
+    /// each holds back all later work until ended or dropped. Times are assigned immediately even
+    /// under [`SubmissionOrder::End`].
+    ///
+    /// If the queue was constructed with [`ParallelQueue::new_multi`], the returned [`Work`]s may
+    /// execute concurrently. One that depends on its predecessor must call
+    /// [`wait_for`](Work::wait_for) with this handle's [`timeline`](Self::timeline) and the
+    /// predecessor's [`time`](Work::time). This is safe even if both are submitted by the same
+    /// [`ParallelQueue::drive`], since the timeline is always signaled before work that waits.
+    ///
+    /// # Safety
+    /// - `device` must match that passed to [`ParallelQueue::new`]
+    /// - [`Work::cmd`] must not be used outside the lifetime of the returned [`Work`]s
+    pub unsafe fn begin_many<'a>(&'a self, device: &'a Device, n: usize) -> Vec<Work<'a>> {
+        unsafe { self.try_begin_many(device, n).unwrap() }
+    }
+
+    /// Fallible version of [`begin_many`](Self::begin_many)
+    ///
+    /// # Safety
+    /// - `device` must match that passed to [`ParallelQueue::new`]
+    /// - [`Work::cmd`] must not be used outside the lifetime of the returned [`Work`]s
+    pub unsafe fn try_begin_many<'a>(
+        &'a self,
+        device: &'a Device,
+        n: usize,
+    ) -> VkResult<Vec<Work<'a>>> {
+        self.shared.check_device()?;
+        let mut cmds = Vec::with_capacity(n);
+        unsafe {
+            for _ in 0..n {
+                match self.begin_cmd(device) {
+                    Ok(cmd) => cmds.push(cmd),
+                    Err(e) => {
+                        for cmd in cmds {
+                            device
+                                .reset_command_buffer(cmd, vk::CommandBufferResetFlags::empty())
+                                .unwrap();
+                            self.spare_cmds.borrow_mut().push(cmd);
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+            let first = self.allocate_times(n as u64);
+            Ok(cmds
+                .into_iter()
+                .zip(first.get()..)
+                .map(|(cmd, time)| self.start(device, cmd, NonZeroU64::new_unchecked(time)))
+                .collect())
+        }
+    }
+
+    /// Obtain a command buffer in the recording state
+    unsafe fn begin_cmd(&self, device: &Device) -> VkResult<vk::CommandBuffer> {
+        let mut spare_cmds = self.spare_cmds.borrow_mut();
+        unsafe {
+            let cmd = match spare_cmds.pop() {
+                Some(cmd) => cmd,
+                None => {
+                    let complete = self.complete(device)?;
+                    reclaim(complete, &mut spare_cmds, &mut self.in_flight.borrow_mut());
+                    if spare_cmds.is_empty() {
+                        spare_cmds.extend(
+                            self.shared.check(
+                                device.allocate_command_buffers(
+                                    &vk::CommandBufferAllocateInfo::default()
+                                        .command_pool(self.cmd_pool)
+                                        .command_buffer_count(32),
+                                ),
+                            )?,
+                        );
+                        self.allocated.set(self.allocated.get() + 32);
+                    }
+                    spare_cmds.pop().unwrap()
+                }
+            };
+            if let Err(e) = self.shared.check(
+                device.begin_command_buffer(
+                    cmd,
+                    &vk::CommandBufferBeginInfo::default()
+                        .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+                ),
+            ) {
+                spare_cmds.push(cmd);
+                return Err(e);
+            }
+            Ok(cmd)
+        }
+    }
+
+    /// Construct work for `cmd` at an allocated `time`
+    unsafe fn start<'a>(
+        &'a self,
+        device: &'a Device,
+        cmd: vk::CommandBuffer,
+        time: NonZeroU64,
+    ) -> Work<'a> {
+        self.in_flight
+            .borrow_mut()
+            .push_back(ErasedWork { cmd, time });
+        let profile = self.shared.profiler.get().and_then(|profiler| {
+            let slot = profiler.reserve(time)?;
+            unsafe {
+                device.cmd_reset_query_pool(cmd, profiler.pool, 2 * slot, 2);
+                device.cmd_write_timestamp2(
+                    cmd,
+                    vk::PipelineStageFlags2::TOP_OF_PIPE,
+                    profiler.pool,
+                    2 * slot,
+                );
+            }
+            Some(Profiled { slot, label: None })
+        });
+        Work::new(self, device, cmd, Some(time), profile)
+    }
+
+    /// Allocate the next `n` times on the timeline, returning the first
+    ///
+    /// Every allocated time must eventually be sent to the queue.
+    fn allocate_times(&self, n: u64) -> NonZeroU64 {
+        let first = self
+            .shared
+            .first_unallocated
+            .fetch_add(n, Ordering::Relaxed);
+        for time in first..first + n {
+            self.shared.allocated(time);
+        }
+        // Safety: times start at 1
+        unsafe { NonZeroU64::new_unchecked(first) }
+    }
+
+    /// Command buffer usage of this handle
+    pub fn stats(&self) -> HandleStats {
+        let secondaries = self.secondaries.borrow();
+        HandleStats {
+            allocated: self.allocated.get(),
+            spare: self.spare_cmds.borrow().len() + secondaries.spare.len(),
+            in_flight: self.in_flight.borrow().len() + secondaries.in_flight.len(),
+        }
+    }
+
+    /// Reclaim command buffers from completed work, then free spare command buffers of each level
+    /// in excess of `max_spare` and return unused memory to the system
+    ///
+    /// Useful for recovering from bursts of work. Returns the resulting usage.
+    ///
+    /// # Safety
+    /// `device` must match that passed to [`ParallelQueue::new`]
+    pub unsafe fn trim(&self, device: &Device, max_spare: usize) -> VkResult<HandleStats> {
+        self.shared.check_device()?;
+        let mut spare_cmds = self.spare_cmds.borrow_mut();
+        let mut in_flight = self.in_flight.borrow_mut();
+        let mut secondaries = self.secondaries.borrow_mut();
+        unsafe {
+            let complete = self.complete(device)?;
+            reclaim(complete, &mut spare_cmds, &mut in_flight);
+            secondaries.reclaim(complete);
+            for spare in [&mut *spare_cmds, &mut secondaries.spare] {
+                if spare.len() > max_spare {
+                    device.free_command_buffers(self.cmd_pool, &spare[max_spare..]);
+                    self.allocated
+                        .set(self.allocated.get() - (spare.len() - max_spare));
+                    spare.truncate(max_spare);
+                }
+            }
+            device.trim_command_pool(self.cmd_pool, vk::CommandPoolTrimFlags::empty());
+        }
+        Ok(HandleStats {
+            allocated: self.allocated.get(),
+            spare: spare_cmds.len() + secondaries.spare.len(),
+            in_flight: in_flight.len() + secondaries.in_flight.len(),
+        })
+    }
+
+    /// Obtain a secondary command buffer that commands may be recorded into on this thread, to be
+    /// executed by a [`Work`] from any handle to the same [`ParallelQueue`]
+    ///
+    /// `inheritance` and `flags` are passed to `vkBeginCommandBuffer`.
+    ///
+    /// # Safety
+    /// - `device` must match that passed to [`ParallelQueue::new`]
+    /// - [`Secondary::cmd`] must not be used outside the lifetime of the returned [`Secondary`]
+    pub unsafe fn begin_secondary<'a>(
+        &'a self,
+        device: &'a Device,
+        inheritance: &vk::CommandBufferInheritanceInfo<'_>,
+        flags: vk::CommandBufferUsageFlags,
+    ) -> Secondary<'a> {
+        unsafe {
+            self.try_begin_secondary(device, inheritance, flags)
+                .unwrap()
+        }
+    }
+
+    /// Fallible version of [`begin_secondary`](Self::begin_secondary)
+    ///
+    /// Fails fast with `VK_ERROR_DEVICE_LOST` if the device has been lost.
+    ///
+    /// # Safety
+    /// - `device` must match that passed to [`ParallelQueue::new`]
+    /// - [`Secondary::cmd`] must not be used outside the lifetime of the returned [`Secondary`]
+    pub unsafe fn try_begin_secondary<'a>(
+        &'a self,
+        device: &'a Device,
+        inheritance: &vk::CommandBufferInheritanceInfo<'_>,
+        flags: vk::CommandBufferUsageFlags,
+    ) -> VkResult<Secondary<'a>> {
+        self.shared.check_device()?;
+        let mut secondaries = self.secondaries.borrow_mut();
+        unsafe {
+            let cmd = match secondaries.spare.pop() {
+                Some(cmd) => cmd,
+                None => {
+                    secondaries.reclaim(self.complete(device)?);
+                    if secondaries.spare.is_empty() {
+                        secondaries.spare.extend(
+                            self.shared.check(
+                                device.allocate_command_buffers(
+                                    &vk::CommandBufferAllocateInfo::default()
+                                        .command_pool(self.cmd_pool)
+                                        .level(vk::CommandBufferLevel::SECONDARY)
+                                        .command_buffer_count(8),
+                                ),
+                            )?,
+                        );
+                        self.allocated.set(self.allocated.get() + 8);
+                    }
+                    secondaries.spare.pop().unwrap()
+                }
+            };
+            if let Err(e) = self.shared.check(
+                device.begin_command_buffer(
+                    cmd,
+                    &vk::CommandBufferBeginInfo::default()
+                        .flags(flags)
+                        .inheritance_info(inheritance),
+                ),
+            ) {
+                secondaries.spare.push(cmd);
+                return Err(e);
+            }
+            let time = Arc::new(AtomicU64::new(UNASSIGNED));
+            secondaries.in_flight.push_back((cmd, time.clone()));
+            Ok(Secondary {
+                inner: ManuallyDrop::new(RecordedSecondary { cmd, time }),
+                handle: PhantomData,
+                device,
+            })
+        }
+    }
+
+    /// The highest value the semaphore has reached
+    unsafe fn complete(&self, device: &Device) -> VkResult<u64> {
+        unsafe {
+            self.shared
+                .check(device.get_semaphore_counter_value(self.shared.semaphore))
+        }
+    }
+
+    /// Create another handle to the same underlying [`ParallelQueue``]
+    ///
+    /// # Safety
+    /// `device` must match that passed to `new`
+    pub unsafe fn handle(&self, device: &Device) -> Handle {
+        unsafe { self.shared.handle(device) }
+    }
+}
+
+/// Move command buffers from work completed by `complete` into `spare_cmds`
+fn reclaim(
+    complete: u64,
+    spare_cmds: &mut Vec<vk::CommandBuffer>,
+    in_flight: &mut VecDeque<ErasedWork>,
+) {
+    while in_flight
+        .front()
+        .map_or(false, |work| work.time.get() <= complete)
+    {
+        spare_cmds.push(in_flight.pop_front().unwrap().cmd);
+    }
+}
+
+impl SecondaryCmds {
+    /// Move command buffers from work completed by `complete` into `spare`
+    fn reclaim(&mut self, complete: u64) {
+        while self
+            .in_flight
+            .front()
+            .is_some_and(|(_, time)| time.load(Ordering::Acquire) <= complete)
+        {
+            self.spare.push(self.in_flight.pop_front().unwrap().0);
+        }
+    }
+}
+
+/// Context for recording commands into a secondary command buffer
+///
+/// As with [`Work`], commands must not be recorded to `cmd` outside the lifetime `'a`.
+pub struct Secondary<'a> {
+    inner: ManuallyDrop<RecordedSecondary>,
+    // As in `Work`, `cmd` is morally a pointer into `cmd_pool` in `Handle`
+    handle: PhantomData<&'a Handle>,
+    device: &'a Device,
+}
+
+impl Secondary<'_> {
+    /// Command buffer to record commands onto
+    pub fn cmd(&self) -> vk::CommandBuffer {
+        self.inner.cmd
+    }
+
+    /// Finish recording, producing a command buffer that may be passed to [`Work::execute`] on
+    /// any thread
+    pub fn end(self) -> RecordedSecondary {
+        let mut this = ManuallyDrop::new(self);
+        // Safety: our lifetime guarantees synchronized access to the command pool behind `cmd`
+        unsafe {
+            this.device.end_command_buffer(this.inner.cmd).unwrap();
+            ManuallyDrop::take(&mut this.inner)
+        }
+    }
+}
+
+impl Drop for Secondary<'_> {
+    fn drop(&mut self) {
+        // Safety: our lifetime guarantees synchronized access to the command pool behind `cmd`
         unsafe {
             self.device
                 .reset_command_buffer(self.inner.cmd, vk::CommandBufferResetFlags::empty())
                 .unwrap();
-            self.handle
-                .shared
-                .send
-                .send(Message::Reset(self.inner.time))
-                .unwrap();
+            ManuallyDrop::drop(&mut self.inner);
         }
     }
 }
 
-#[derive(Copy, Clone)]
-struct ErasedWork {
+/// A fully recorded secondary command buffer, ready to be [`execute`](Work::execute)d
+///
+/// Dropping it without executing returns the command buffer to its [`Handle`] for reuse.
+pub struct RecordedSecondary {
     cmd: vk::CommandBuffer,
-    time: NonZeroU64,
+    /// Shared with the owning [`Handle`] to determine when `cmd` may be reused
+    time: Arc<AtomicU64>,
 }
 
-pub struct Handle {
+impl RecordedSecondary {
+    pub fn cmd(&self) -> vk::CommandBuffer {
+        self.cmd
+    }
+
+    /// Take responsibility for assigning the time after which `cmd` may be reused
+    fn into_time(self) -> Arc<AtomicU64> {
+        let this = ManuallyDrop::new(self);
+        // Safety: `this` is never accessed or dropped again
+        unsafe { ptr::read(&this.time) }
+    }
+}
+
+impl Drop for RecordedSecondary {
+    fn drop(&mut self) {
+        self.time.store(ABANDONED, Ordering::Release);
+    }
+}
+
+/// The measured GPU execution time of a [`Work`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct WorkTiming {
+    /// The work's [`time`](Work::time)
+    pub time: NonZeroU64,
+    /// The label passed to [`Handle::begin_labeled`], if any
+    pub label: Option<CString>,
+    /// Time elapsed between the start and end of the work's command buffer
+    pub duration: Duration,
+}
+
+impl WorkTiming {
+    /// Compute the timing of the work at `time` from its start and end timestamp query results
+    ///
+    /// This is the conversion applied to results read back from the device, exposed so that code
+    /// consuming timings can be tested against synthetic query results. `timestamp_period` and
+    /// `timestamp_valid_bits` are as passed to [`ParallelQueue::enable_profiling`].
+    pub fn from_timestamps(
+        time: NonZeroU64,
+        label: Option<CString>,
+        [start, end]: [u64; 2],
+        timestamp_period: f32,
+        timestamp_valid_bits: u32,
+    ) -> Self {
+        Self {
+            time,
+            label,
+            duration: ticks_to_duration(start, end, timestamp_period, timestamp_valid_bits),
+        }
+    }
+}
+
+/// Where a time was allocated, recorded in diagnostics mode
+struct Allocation {
+    thread: String,
+    backtrace: Arc<Backtrace>,
+    at: Instant,
+}
+
+/// A [`Work`] that has been begun but not ended or dropped, preventing submission of all later
+/// work
+///
+/// See [`ParallelQueue::stall`].
+#[derive(Debug, Clone)]
+pub struct Stall {
+    /// The stalled work's [`time`](Work::time)
+    pub time: NonZeroU64,
+    /// Number of later times that have been allocated, and hence cannot be submitted
+    pub blocked: u64,
+    /// Time since the work was begun
+    pub elapsed: Duration,
+    /// Name, or ID if unnamed, of the thread that began the work
+    pub thread: String,
+    /// Where the work was begun, if backtraces were enabled
+    pub backtrace: Arc<Backtrace>,
+}
+
+impl fmt::Display for Stall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "work at time {} begun on thread {} has not been submitted after {:?}, blocking {} later",
+            self.time, self.thread, self.elapsed, self.blocked
+        )?;
+        if self.backtrace.status() == BacktraceStatus::Captured {
+            write!(f, "\n{}", self.backtrace)?;
+        }
+        Ok(())
+    }
+}
+
+/// Query slots shared between a [`ParallelQueue`] and its handles for profiling
+struct Profiler {
+    pool: vk::QueryPool,
+    /// Number of slots, each consisting of two queries
+    capacity: u32,
+    /// The lowest time whose slot might not be resolved yet. Slots of earlier times may be reused.
+    first_unresolved: AtomicU64,
+}
+
+impl Profiler {
+    /// Select the slot for work at `time`, if it's free
+    fn reserve(&self, time: NonZeroU64) -> Option<u32> {
+        let first_unresolved = self.first_unresolved.load(Ordering::Acquire);
+        (time.get() < first_unresolved + u64::from(self.capacity))
+            .then(|| (time.get() % u64::from(self.capacity)) as u32)
+    }
+}
+
+/// The query slot and label of a profiled [`Work`]
+struct Profiled {
+    slot: u32,
+    label: Option<CString>,
+}
+
+/// [`ParallelQueue`]-side profiling state
+struct Profiling {
+    timestamp_period: f32,
+    timestamp_valid_bits: u32,
+    /// Submitted profiled work, in timeline order
+    pending: VecDeque<(NonZeroU64, Profiled)>,
+    timings: Vec<WorkTiming>,
+}
+
+impl Profiling {
+    fn new(timestamp_period: f32, timestamp_valid_bits: u32) -> Self {
+        Self {
+            timestamp_period,
+            timestamp_valid_bits,
+            pending: VecDeque::new(),
+            timings: Vec::new(),
+        }
+    }
+
+    /// The lowest time whose slot may still be in use, given that no later work is submitted
+    fn first_unresolved(&self, first_unsubmitted: u64) -> u64 {
+        self.pending
+            .front()
+            .map_or(first_unsubmitted, |(time, _)| time.get())
+    }
+
+    /// Compute timings for work completed by `complete`, using `read` to fetch the start and end
+    /// timestamps from a slot
+    fn resolve(
+        &mut self,
+        complete: u64,
+        mut read: impl FnMut(u32) -> VkResult<[u64; 2]>,
+    ) -> VkResult<()> {
+        while let Some(&(time, ref profiled)) = self.pending.front()
+            && time.get() <= complete
+        {
+            let timestamps = read(profiled.slot)?;
+            let (_, profiled) = self.pending.pop_front().unwrap();
+            self.timings.push(WorkTiming::from_timestamps(
+                time,
+                profiled.label,
+                timestamps,
+                self.timestamp_period,
+                self.timestamp_valid_bits,
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Convert the interval between two timestamps into wall-clock time
+fn ticks_to_duration(start: u64, end: u64, period: f32, valid_bits: u32) -> Duration {
+    let mask = if valid_bits >= 64 {
+        !0
+    } else {
+        (1 << valid_bits) - 1
+    };
+    // Timestamps may wrap around within the valid bits
+    let ticks = end.wrapping_sub(start) & mask;
+    Duration::from_nanos((ticks as f64 * f64::from(period)) as u64)
+}
+
+/// Command buffer usage of a [`Handle`]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct HandleStats {
+    /// Total command buffers allocated from the handle's command pool
+    pub allocated: usize,
+    /// Command buffers ready for immediate reuse
+    pub spare: usize,
+    /// Command buffers being recorded, or awaiting execution or reclamation
+    pub in_flight: usize,
+}
+
+/// A thread-safe pool of [`Handle`]s for use by short-lived tasks on worker threads
+///
+/// Each concurrent user borrows a handle of its own, which is created on demand and returned to the
+/// pool when the borrow ends. The pool therefore holds roughly one handle per worker thread,
+/// regardless of how often threads are spawned and retired.
+///
+/// Must be [`destroy`](Self::destroy)ed before being dropped, or the command pools of its handles
+/// leak. In debug builds, dropping a pool that still holds handles panics.
+pub struct HandlePool {
     shared: Arc<Shared>,
-    cmd_pool: vk::CommandPool,
-    spare_cmds: RefCell<Vec<vk::CommandBuffer>>,
-    in_flight: RefCell<VecDeque<ErasedWork>>,
+    idle: Mutex<Vec<Handle>>,
 }
 
-impl Handle {
+impl HandlePool {
+    fn new(shared: Arc<Shared>) -> Self {
+        Self {
+            shared,
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Borrow an idle handle, or create a new one if none are available
+    ///
     /// # Safety
-    /// `device` must match that passed to [`ParallelQueue::new`] and no work may be in flight
+    /// `device` must match that passed to [`ParallelQueue::new`]
+    pub unsafe fn get(&self, device: &Device) -> PooledHandle<'_> {
+        let handle = self.idle.lock().unwrap().pop();
+        PooledHandle {
+            pool: self,
+            handle: ManuallyDrop::new(
+                handle.unwrap_or_else(|| unsafe { self.shared.handle(device) }),
+            ),
+        }
+    }
+
+    /// Number of handles not currently borrowed
+    pub fn idle(&self) -> usize {
+        self.idle.lock().unwrap().len()
+    }
+
+    /// Destroy all handles in the pool
+    ///
+    /// # Safety
+    /// `device` must match that passed to [`ParallelQueue::new`] and no work from this pool may be
+    /// in flight, as determined by calling [`ParallelQueue::drain`] after all work has been
+    /// submitted.
     pub unsafe fn destroy(&mut self, device: &Device) {
-        unsafe {
-            device.destroy_command_pool(self.cmd_pool, None);
+        for mut handle in self.idle.get_mut().unwrap().drain(..) {
+            unsafe {
+                handle.destroy(device);
+            }
         }
     }
+}
 
-    #[inline]
-    pub fn semaphore(&self) -> vk::Semaphore {
-        self.shared.semaphore
+impl Drop for HandlePool {
+    fn drop(&mut self) {
+        let idle = self.idle.get_mut().unwrap_or_else(|e| e.into_inner()).len();
+        if cfg!(debug_assertions) && !thread::panicking() && idle != 0 {
+            panic!("handle pool dropped without being destroyed, leaking {idle} handles");
+        }
+    }
+}
+
+/// A [`Handle`] borrowed from a [`HandlePool`]
+pub struct PooledHandle<'a> {
+    pool: &'a HandlePool,
+    handle: ManuallyDrop<Handle>,
+}
+
+impl Deref for PooledHandle<'_> {
+    type Target = Handle;
+
+    fn deref(&self) -> &Handle {
+        &self.handle
+    }
+}
+
+impl Drop for PooledHandle<'_> {
+    fn drop(&mut self) {
+        // Safety: `handle` is not accessed again
+        let handle = unsafe { ManuallyDrop::take(&mut self.handle) };
+        self.pool.idle.lock().unwrap().push(handle);
+    }
+}
+
+/// A [`Handle`] that can be cloned and shared between threads or async tasks to record
+/// [`OwnedWork`]
+///
+/// Access to the command pool is serialized by a mutex, held only while beginning, recording, or
+/// ending work.
+#[derive(Clone)]
+pub struct SharedHandle {
+    handle: Arc<Mutex<Handle>>,
+    device: Arc<Device>,
+}
+
+impl SharedHandle {
+    /// # Safety
+    /// `device` must match that passed to [`ParallelQueue::new`]
+    pub unsafe fn new(handle: Handle, device: Arc<Device>) -> Self {
+        Self {
+            handle: Arc::new(Mutex::new(handle)),
+            device,
+        }
     }
 
     #[inline]
-    pub fn cmd_pool(&self) -> vk::CommandPool {
-        self.cmd_pool
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
     }
 
-    /// Obtain a command buffer that commands may be recorded into
+    /// Identifies the queue for cross-queue dependencies
+    pub fn timeline(&self) -> Timeline {
+        self.lock().timeline()
+    }
+
+    /// Begin recording work that may be held across `.await`s or sent between threads
+    pub fn begin(&self) -> OwnedWork {
+        self.try_begin().unwrap()
+    }
+
+    /// Fallible version of [`begin`](Self::begin)
+    pub fn try_begin(&self) -> VkResult<OwnedWork> {
+        let handle = self.lock();
+        // Safety: `device` matches per `new`, and `OwnedWork` only exposes `cmd` while holding the
+        // lock
+        let state = unsafe { handle.try_begin(&self.device)?.into_state() };
+        Ok(OwnedWork {
+            state,
+            handle: self.clone(),
+        })
+    }
+
+    /// Recover the underlying handle, e.g. for destruction, if this is the last clone and no
+    /// [`OwnedWork`] is outstanding
+    pub fn into_inner(self) -> Option<Handle> {
+        Arc::into_inner(self.handle).map(|x| x.into_inner().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Lock the handle, ignoring poisoning
     ///
-    /// The [`Work`] must be dropped or [`end`](Work::end)ed soon to avoid stalling the queue.
+    /// A panic in a closure passed to [`OwnedWork::record`] poisons the lock but cannot leave the
+    /// handle itself inconsistent. Propagating it would make every other user of the handle panic,
+    /// and abort the process when the `OwnedWork` is dropped during unwinding.
+    fn lock(&self) -> MutexGuard<'_, Handle> {
+        self.handle.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Like [`Work`], but `'static`, so it can be held across `.await`s or moved into other threads
+///
+/// Commands are recorded with [`record`](Self::record), which holds the [`SharedHandle`]'s lock
+/// for the duration.
+pub struct OwnedWork {
+    state: WorkState,
+    handle: SharedHandle,
+}
+
+impl OwnedWork {
+    /// See [`Work::time`]
+    pub fn time(&self) -> NonZeroU64 {
+        self.state.time()
+    }
+
+    /// Record commands onto the work's command buffer with `f`
     ///
     /// # Safety
-    /// - `device` must match that passed to [`ParallelQueue::new`]
-    /// - [`Work::cmd`] must not be used outside the lifetime of the returned [`Work`]
-    pub unsafe fn begin<'a>(&'a self, device: &'a Device) -> Work<'a> {
-        let mut spare_cmds = self.spare_cmds.borrow_mut();
-        let mut in_flight = self.in_flight.borrow_mut();
+    /// The command buffer passed to `f` must not be used after `f` returns
+    pub unsafe fn record<R>(&mut self, f: impl FnOnce(&Device, vk::CommandBuffer) -> R) -> R {
+        let _guard = self.handle.lock();
+        f(&self.handle.device, self.state.cmd)
+    }
+
+    /// See [`Work::wait`]
+    ///
+    /// # Safety
+    /// See [`Work::wait`]
+    pub unsafe fn wait(
+        &mut self,
+        semaphore: vk::Semaphore,
+        value: u64,
+        stage_mask: vk::PipelineStageFlags2,
+    ) {
+        self.state.wait(semaphore, value, stage_mask);
+    }
+
+    /// See [`Work::signal_stage_mask`]
+    pub fn signal_stage_mask(&mut self, stage_mask: vk::PipelineStageFlags2) {
+        self.state.signal_stage_mask = stage_mask;
+    }
+
+    /// See [`Work::keep_alive`]
+    ///
+    /// # Safety
+    /// See [`Work::keep_alive`]
+    pub unsafe fn keep_alive(&mut self, resources: impl VisitHandles) {
+        self.state.keep_alive(resources);
+    }
+
+    /// See [`Work::on_complete`]
+    pub fn on_complete(&mut self, f: impl FnOnce() + Send + 'static) {
+        self.state.callbacks.push(Box::new(f));
+    }
+
+    /// See [`Work::wait_for`]
+    ///
+    /// # Safety
+    /// See [`Work::wait_for`]
+    pub unsafe fn wait_for(
+        &mut self,
+        src: Timeline,
+        time: NonZeroU64,
+        stage_mask: vk::PipelineStageFlags2,
+    ) {
+        self.state.wait(src.semaphore, time.get(), stage_mask);
+    }
+
+    /// See [`Work::release`]
+    ///
+    /// # Safety
+    /// See [`Work::release`]
+    pub unsafe fn release(
+        &self,
+        dst: Timeline,
+        transfer: &OwnershipTransfer,
+        src_stage_mask: vk::PipelineStageFlags2,
+        src_access_mask: vk::AccessFlags2,
+    ) {
+        let handle = self.handle.lock();
         unsafe {
-            let time = NonZeroU64::new_unchecked(
-                self.shared
-                    .first_unallocated
-                    .fetch_add(1, Ordering::Relaxed),
+            self.state.release(
+                &handle,
+                &self.handle.device,
+                dst,
+                transfer,
+                src_stage_mask,
+                src_access_mask,
             );
-            let cmd = match spare_cmds.pop() {
-                Some(cmd) => cmd,
-                None => {
-                    let complete = device
-                        .get_semaphore_counter_value(self.shared.semaphore)
-                        .unwrap();
-                    while in_flight
-                        .front()
-                        .map_or(false, |work| work.time.get() <= complete)
-                    {
-                        spare_cmds.push(in_flight.pop_front().unwrap().cmd);
-                    }
-                    if spare_cmds.is_empty() {
-                        spare_cmds.extend(
-                            device
-                                .allocate_command_buffers(
-                                    &vk::CommandBufferAllocateInfo::default()
-                                        .command_pool(self.cmd_pool)
-                                        .command_buffer_count(32),
-                                )
-                                .unwrap(),
-                        );
-                    }
-                    spare_cmds.pop().unwrap()
-                }
-            };
-            device
-                .begin_command_buffer(
-                    cmd,
-                    &vk::CommandBufferBeginInfo::default()
-                        .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
-                )
-                .unwrap();
-            let work = ErasedWork { cmd, time };
-            in_flight.push_back(work);
-            Work {
-                inner: work,
-                handle: self,
-                device,
-            }
         }
     }
 
-    /// Create another handle to the same underlying [`ParallelQueue``]
+    /// See [`Work::acquire`]
     ///
     /// # Safety
-    /// `device` must match that passed to `new`
-    pub unsafe fn handle(&self, device: &Device) -> Handle {
-        unsafe { self.shared.handle(device) }
+    /// See [`Work::acquire`]
+    pub unsafe fn acquire(
+        &mut self,
+        src: Timeline,
+        time: NonZeroU64,
+        transfer: &OwnershipTransfer,
+        dst_stage_mask: vk::PipelineStageFlags2,
+        dst_access_mask: vk::AccessFlags2,
+    ) {
+        self.state.wait(src.semaphore, time.get(), dst_stage_mask);
+        let handle = self.handle.lock();
+        unsafe {
+            self.state.acquire(
+                &handle,
+                &self.handle.device,
+                src,
+                transfer,
+                dst_stage_mask,
+                dst_access_mask,
+            );
+        }
+    }
+
+    /// See [`Work::execute`]
+    ///
+    /// # Safety
+    /// See [`Work::execute`]
+    pub unsafe fn execute(&mut self, secondaries: impl IntoIterator<Item = RecordedSecondary>) {
+        let _guard = self.handle.lock();
+        unsafe {
+            self.state.execute(&self.handle.device, secondaries);
+        }
+    }
+
+    /// See [`Work::end`]
+    pub fn end(self) -> Completion {
+        let this = ManuallyDrop::new(self);
+        // Safety: `this` is never accessed or dropped again
+        let (state, shared) = unsafe { (ptr::read(&this.state), ptr::read(&this.handle)) };
+        let handle = shared.lock();
+        // Safety: `handle` was created with `device`, and is locked
+        unsafe { state.end(&handle, &shared.device) }
+    }
+}
+
+impl Drop for OwnedWork {
+    fn drop(&mut self) {
+        let handle = self.handle.lock();
+        // Safety: `handle` was created with `device`, and is locked
+        unsafe {
+            self.state.abandon(&handle, &self.handle.device);
+        }
     }
 }
 
 enum Message {
-    Execute(ErasedWork),
-    Reset(NonZeroU64),
+    Execute(Submission),
+    /// Work that was dropped, and handles it was keeping alive
+    Reset(NonZeroU64, Vec<(vk::ObjectType, u64)>),
 }
 
 impl Message {
     fn time(&self) -> NonZeroU64 {
         match *self {
-            Message::Execute(ref work) => work.time,
-            Message::Reset(time) => time,
+            Message::Execute(ref submission) => submission.work.time,
+            Message::Reset(time, _) => time,
         }
     }
 }
@@ -479,3 +2816,289 @@ impl PartialOrd for Message {
         Some(self.cmp(other))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ash::vk::Handle as _;
+
+    use super::*;
+
+    const WORK: Slot = Slot::Execute { waits: false };
+    const WAIT: Slot = Slot::Execute { waits: true };
+    const RESET: Slot = Slot::Reset;
+
+    fn batch(slots: Range<usize>, signal: Option<u64>) -> PlannedBatch {
+        PlannedBatch { slots, signal }
+    }
+
+    #[test]
+    fn unbounded() {
+        assert_eq!(
+            plan(BatchPolicy::Unbounded, 5, &[WORK, RESET, WORK]),
+            vec![vec![batch(0..3, Some(7))]]
+        );
+    }
+
+    #[test]
+    fn only_resets() {
+        assert_eq!(
+            plan(BatchPolicy::PerWork, 1, &[RESET, RESET]),
+            vec![vec![batch(0..2, Some(2))]]
+        );
+    }
+
+    #[test]
+    fn waits_split_batches() {
+        assert_eq!(
+            plan(BatchPolicy::Unbounded, 1, &[WORK, WAIT, WORK, WAIT, WAIT]),
+            vec![vec![
+                batch(0..1, Some(1)),
+                batch(1..2, None),
+                batch(2..3, Some(3)),
+                batch(3..4, Some(4)),
+                batch(4..5, Some(5)),
+            ]]
+        );
+    }
+
+    #[test]
+    fn wait_on_same_call() {
+        // Work waiting on its predecessor must not sit in front of the only signal that could
+        // satisfy it
+        for policy in [
+            BatchPolicy::Unbounded,
+            BatchPolicy::Limit(NonZeroUsize::new(2).unwrap()),
+        ] {
+            assert_eq!(
+                plan(policy, 7, &[WORK, WAIT]),
+                vec![vec![batch(0..1, Some(7)), batch(1..2, Some(8))]]
+            );
+        }
+        assert_eq!(
+            plan(BatchPolicy::Unbounded, 1, &[WORK, RESET, WAIT, WORK]),
+            vec![vec![
+                batch(0..2, Some(2)),
+                batch(2..3, None),
+                batch(3..4, Some(4))
+            ]]
+        );
+    }
+
+    #[test]
+    fn begin_many_chain() {
+        // A chain of three, each waiting on the last, ended before a single `drive`
+        for policy in [
+            BatchPolicy::Unbounded,
+            BatchPolicy::Limit(NonZeroUsize::new(3).unwrap()),
+            BatchPolicy::SignalEach,
+        ] {
+            assert_eq!(
+                plan(policy, 4, &[WORK, WAIT, WAIT]),
+                vec![vec![
+                    batch(0..1, Some(4)),
+                    batch(1..2, Some(5)),
+                    batch(2..3, Some(6)),
+                ]]
+            );
+        }
+    }
+
+    #[test]
+    fn gated_signals() {
+        let semaphore = vk::Semaphore::from_raw(1);
+        let signal = |value| Some(vk::SemaphoreSubmitInfo::default().value(value));
+        let mut batches = vec![
+            Batch {
+                cmds: vec![vk::CommandBufferSubmitInfo::default()],
+                signal: signal(3),
+                ..Batch::default()
+            },
+            Batch {
+                cmds: vec![vk::CommandBufferSubmitInfo::default()],
+                ..Batch::default()
+            },
+            Batch {
+                cmds: vec![vk::CommandBufferSubmitInfo::default()],
+                signal: signal(5),
+                ..Batch::default()
+            },
+        ];
+        gate_signals(&mut batches, semaphore, 2);
+        let summary = batches
+            .iter()
+            .map(|batch| {
+                (
+                    batch
+                        .waits
+                        .iter()
+                        .map(|wait| (wait.semaphore, wait.value))
+                        .collect::<Vec<_>>(),
+                    batch.cmds.len(),
+                    batch.signal.map(|signal| signal.value),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (vec![], 1, None),
+                (vec![(semaphore, 2)], 0, Some(3)),
+                (vec![], 1, None),
+                (vec![], 1, None),
+                (vec![(semaphore, 2)], 0, Some(5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn per_work() {
+        assert_eq!(
+            plan(BatchPolicy::PerWork, 1, &[RESET, WORK, WORK, RESET]),
+            vec![vec![batch(0..2, Some(2))], vec![batch(2..4, Some(4))]]
+        );
+    }
+
+    #[test]
+    fn limit() {
+        assert_eq!(
+            plan(
+                BatchPolicy::Limit(NonZeroUsize::new(2).unwrap()),
+                1,
+                &[WORK, WORK, WAIT, WORK, WORK]
+            ),
+            vec![
+                vec![batch(0..2, Some(2))],
+                vec![batch(2..3, None), batch(3..4, Some(4))],
+                vec![batch(4..5, Some(5))],
+            ]
+        );
+    }
+
+    #[test]
+    fn tick_conversion() {
+        assert_eq!(ticks_to_duration(10, 30, 1.5, 64), Duration::from_nanos(30));
+        // Wrapping within 8 valid bits
+        assert_eq!(ticks_to_duration(250, 4, 1.0, 8), Duration::from_nanos(10));
+    }
+
+    #[test]
+    fn resolve_timings() {
+        let mut profiling = Profiling::new(2.0, 64);
+        for (time, label) in [(2, None), (3, Some(c"upload")), (5, None)] {
+            profiling.pending.push_back((
+                NonZeroU64::new(time).unwrap(),
+                Profiled {
+                    slot: time as u32 % 4,
+                    label: label.map(CStr::to_owned),
+                },
+            ));
+        }
+        let mut reads = Vec::new();
+        profiling
+            .resolve(4, |slot| {
+                reads.push(slot);
+                Ok([100, 100 + u64::from(slot)])
+            })
+            .unwrap();
+        assert_eq!(reads, [2, 3]);
+        assert_eq!(
+            profiling.timings,
+            [
+                WorkTiming {
+                    time: NonZeroU64::new(2).unwrap(),
+                    label: None,
+                    duration: Duration::from_nanos(4),
+                },
+                WorkTiming {
+                    time: NonZeroU64::new(3).unwrap(),
+                    label: Some(c"upload".to_owned()),
+                    duration: Duration::from_nanos(6),
+                },
+            ]
+        );
+        assert_eq!(profiling.pending.len(), 1);
+
+        // Failures leave work pending for a later attempt
+        assert_eq!(
+            profiling.resolve(5, |_| Err(vk::Result::ERROR_DEVICE_LOST)),
+            Err(vk::Result::ERROR_DEVICE_LOST)
+        );
+        assert_eq!(profiling.pending.len(), 1);
+    }
+
+    #[test]
+    fn signal_each() {
+        assert_eq!(
+            plan(BatchPolicy::SignalEach, 1, &[WORK, RESET, WORK, WAIT]),
+            vec![vec![
+                batch(0..2, Some(2)),
+                batch(2..3, Some(3)),
+                batch(3..4, Some(4)),
+            ]]
+        );
+    }
+
+    #[test]
+    fn park_wakes_on_either() {
+        let semaphores = [vk::Semaphore::from_raw(1), vk::Semaphore::from_raw(2)];
+        let info = park_wait_info(&semaphores, &[1, 1]);
+        assert!(info.flags == vk::SemaphoreWaitFlags::ANY);
+        assert_eq!(info.semaphore_count, 2);
+    }
+
+    #[test]
+    fn profiler_slot_reuse() {
+        const CAPACITY: u32 = 4;
+        let profiler = Profiler {
+            pool: vk::QueryPool::null(),
+            capacity: CAPACITY,
+            first_unresolved: AtomicU64::new(1),
+        };
+        let mut profiling = Profiling::new(1.0, 64);
+        let mut next_time = 1;
+        let mut first_unsubmitted = 1;
+        // Begun but unsubmitted work, and the slot it reserved, if any
+        let mut begun = VecDeque::new();
+        // Slots that may not be reused, by the time that reserved them
+        let mut held = BTreeMap::<u64, u32>::new();
+        let mut reserved = 0;
+        for step in 0..100u64 {
+            for _ in 0..step % 3 + 1 {
+                let time = NonZeroU64::new(next_time).unwrap();
+                next_time += 1;
+                let slot = profiler.reserve(time);
+                if let Some(slot) = slot {
+                    assert!(
+                        held.values().all(|&x| x != slot),
+                        "slot {slot} reused by time {time} while held: {held:?}"
+                    );
+                    held.insert(time.get(), slot);
+                    reserved += 1;
+                }
+                begun.push_back((time, slot));
+            }
+            if step % 2 == 0 {
+                // Submit everything begun so far
+                for (time, slot) in begun.drain(..) {
+                    if let Some(slot) = slot {
+                        profiling
+                            .pending
+                            .push_back((time, Profiled { slot, label: None }));
+                    }
+                    first_unsubmitted = time.get() + 1;
+                }
+            }
+            if step % 5 == 0 {
+                // Complete roughly half of the submitted work
+                let complete = first_unsubmitted - 1 - (first_unsubmitted - 1) % 2;
+                profiling.resolve(complete, |_| Ok([0, 0])).unwrap();
+                held.retain(|&time, _| time > complete);
+            }
+            profiler.first_unresolved.store(
+                profiling.first_unresolved(first_unsubmitted),
+                Ordering::Release,
+            );
+        }
+        assert!(reserved > 2 * CAPACITY, "slots were never reused");
+    }
+}
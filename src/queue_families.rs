@@ -0,0 +1,197 @@
+use ash::{Device, ext, vk};
+
+use crate::ParallelQueue;
+
+/// Queue families chosen for each role, preferring dedicated hardware for async work
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct QueueFamilies {
+    /// Family supporting graphics and compute
+    pub graphics: u32,
+    /// Family supporting compute but not graphics, if any
+    pub compute: Option<u32>,
+    /// Family supporting transfer but neither graphics nor compute, if any
+    pub transfer: Option<u32>,
+}
+
+impl QueueFamilies {
+    /// Choose families from those reported by `vkGetPhysicalDeviceQueueFamilyProperties`
+    ///
+    /// Returns `None` if no family supports both graphics and compute.
+    pub fn select(families: &[vk::QueueFamilyProperties]) -> Option<Self> {
+        let find = |required: vk::QueueFlags, excluded: vk::QueueFlags| {
+            families
+                .iter()
+                .position(|family| {
+                    family.queue_count > 0
+                        && family.queue_flags.contains(required)
+                        && !family.queue_flags.intersects(excluded)
+                })
+                .map(|index| index as u32)
+        };
+        Some(Self {
+            graphics: find(
+                vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE,
+                vk::QueueFlags::empty(),
+            )?,
+            compute: find(vk::QueueFlags::COMPUTE, vk::QueueFlags::GRAPHICS),
+            // Transfer support is implied by graphics or compute, so may not be advertised
+            transfer: find(
+                vk::QueueFlags::TRANSFER,
+                vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE,
+            ),
+        })
+    }
+
+    /// Queues to request in `VkDeviceCreateInfo`, one per selected family
+    pub fn queue_create_infos(&self) -> Vec<vk::DeviceQueueCreateInfo<'static>> {
+        const PRIORITIES: &[f32] = &[1.0];
+        [Some(self.graphics), self.compute, self.transfer]
+            .into_iter()
+            .flatten()
+            .map(|family| {
+                vk::DeviceQueueCreateInfo::default()
+                    .queue_family_index(family)
+                    .queue_priorities(PRIORITIES)
+            })
+            .collect()
+    }
+
+    /// Construct a [`ParallelQueue`] for each selected family
+    ///
+    /// # Safety
+    /// - `device` must have been created with [`queue_create_infos`](Self::queue_create_infos), and
+    ///   meet the requirements of [`ParallelQueue::new`]
+    /// - `debug_utils` must be associated with `device`
+    pub unsafe fn create_queues(
+        &self,
+        device: &Device,
+        debug_utils: Option<&ext::debug_utils::Device>,
+    ) -> Queues {
+        let create = |family| unsafe {
+            ParallelQueue::new(
+                device,
+                family,
+                device.get_device_queue(family, 0),
+                debug_utils,
+            )
+        };
+        Queues {
+            graphics: create(self.graphics),
+            compute: self.compute.map(create),
+            transfer: self.transfer.map(create),
+        }
+    }
+}
+
+/// A [`ParallelQueue`] for each role selected by [`QueueFamilies`]
+pub struct Queues {
+    pub graphics: ParallelQueue,
+    pub compute: Option<ParallelQueue>,
+    pub transfer: Option<ParallelQueue>,
+}
+
+impl Queues {
+    /// Queue for async compute work, falling back to `graphics`
+    pub fn compute_or_graphics(&mut self) -> &mut ParallelQueue {
+        self.compute.as_mut().unwrap_or(&mut self.graphics)
+    }
+
+    /// Queue for uploads and other copies, falling back to `compute`, then `graphics`
+    pub fn transfer_or_compute(&mut self) -> &mut ParallelQueue {
+        match self.transfer {
+            Some(ref mut transfer) => transfer,
+            None => self.compute_or_graphics(),
+        }
+    }
+
+    /// # Safety
+    /// `device` must match that passed to [`QueueFamilies::create_queues`] and no work may be in
+    /// flight on any queue
+    pub unsafe fn destroy(&mut self, device: &Device) {
+        unsafe {
+            self.graphics.destroy(device);
+            for queue in [&mut self.compute, &mut self.transfer]
+                .into_iter()
+                .flatten()
+            {
+                queue.destroy(device);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn family(queue_flags: vk::QueueFlags, queue_count: u32) -> vk::QueueFamilyProperties {
+        vk::QueueFamilyProperties::default()
+            .queue_flags(queue_flags)
+            .queue_count(queue_count)
+    }
+
+    const G: vk::QueueFlags = vk::QueueFlags::GRAPHICS;
+    const C: vk::QueueFlags = vk::QueueFlags::COMPUTE;
+    const T: vk::QueueFlags = vk::QueueFlags::TRANSFER;
+
+    #[test]
+    fn dedicated() {
+        let families = [family(G | C | T, 16), family(T, 2), family(C | T, 8)];
+        assert_eq!(
+            QueueFamilies::select(&families),
+            Some(QueueFamilies {
+                graphics: 0,
+                compute: Some(2),
+                transfer: Some(1),
+            })
+        );
+        assert_eq!(
+            QueueFamilies::select(&families)
+                .unwrap()
+                .queue_create_infos()
+                .iter()
+                .map(|x| x.queue_family_index)
+                .collect::<Vec<_>>(),
+            [0, 2, 1]
+        );
+    }
+
+    #[test]
+    fn unified() {
+        let families = [family(G | C | T, 1)];
+        let selected = QueueFamilies::select(&families).unwrap();
+        assert_eq!(
+            selected,
+            QueueFamilies {
+                graphics: 0,
+                compute: None,
+                transfer: None,
+            }
+        );
+        assert_eq!(selected.queue_create_infos().len(), 1);
+    }
+
+    #[test]
+    fn skip_empty_and_incomplete() {
+        let families = [
+            family(G | C | T, 0),
+            family(G, 1),
+            family(C, 0),
+            family(G | C, 1),
+            family(T, 0),
+        ];
+        assert_eq!(
+            QueueFamilies::select(&families),
+            Some(QueueFamilies {
+                graphics: 3,
+                compute: None,
+                transfer: None,
+            })
+        );
+    }
+
+    #[test]
+    fn no_graphics() {
+        assert_eq!(QueueFamilies::select(&[family(C | T, 4)]), None);
+    }
+}
@@ -33,7 +33,33 @@ macro_rules! impl_handles {
     };
 }
 
-impl_handles!(Buffer, Image, ImageView, DeviceMemory, Framebuffer,);
+impl_handles!(
+    Buffer,
+    BufferView,
+    Image,
+    ImageView,
+    DeviceMemory,
+    Framebuffer,
+    Sampler,
+    SamplerYcbcrConversion,
+    Pipeline,
+    PipelineLayout,
+    PipelineCache,
+    DescriptorSetLayout,
+    DescriptorPool,
+    DescriptorUpdateTemplate,
+    QueryPool,
+    Semaphore,
+    Fence,
+    Event,
+    RenderPass,
+    ShaderModule,
+    CommandPool,
+    PrivateDataSlot,
+    SwapchainKHR,
+    AccelerationStructureKHR,
+    DebugUtilsMessengerEXT,
+);
 
 pub unsafe fn set_names<T: VisitHandles>(pfn: &debug_utils::Device, x: &T, name: &CStr) {
     struct Visitor<'a>(&'a debug_utils::Device, &'a CStr);